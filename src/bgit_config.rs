@@ -0,0 +1,82 @@
+use git2::{Config, Repository};
+use std::path::PathBuf;
+
+/// Team wide bgit settings committed to `{RepositoryBase}/.bgit/config`
+///
+/// The file uses the git config syntax, so it can be edited with
+/// `git config -f .bgit/config <key> <value>`, e.g.
+/// ```text
+/// [secrets]
+///     hexEntropyThreshold = 0.97
+///     base64EntropyThreshold = 0.93
+/// ```
+/// Every getter returns `None` when the file or the key is missing, so rules
+/// fall back to their defaults.
+pub(crate) struct BGitConfig {
+    config: Option<Config>,
+}
+
+impl BGitConfig {
+    pub(crate) fn load(repo: &Repository) -> Self {
+        let config = Self::config_path(repo)
+            .filter(|path| path.is_file())
+            .and_then(|path| Config::open(&path).and_then(|mut c| c.snapshot()).ok());
+        BGitConfig { config }
+    }
+
+    pub(crate) fn config_path(repo: &Repository) -> Option<PathBuf> {
+        repo.workdir()
+            .map(|workdir| workdir.join(".bgit").join("config"))
+    }
+
+    pub(crate) fn get_string(&self, key: &str) -> Option<String> {
+        self.config.as_ref()?.get_string(key).ok()
+    }
+
+    /// Every value of a key that may be given multiple times, in file order
+    pub(crate) fn get_all(&self, key: &str) -> Vec<String> {
+        let mut values = Vec::new();
+        if let Some(config) = self.config.as_ref() {
+            if let Ok(entries) = config.multivar(key, None) {
+                let _ = entries.for_each(|entry| {
+                    if let Some(value) = entry.value() {
+                        values.push(value.to_owned());
+                    }
+                });
+            }
+        }
+        values
+    }
+
+    /// Integer value, git config suffixes like `5m` or `1g` are supported
+    pub(crate) fn get_i64(&self, key: &str) -> Option<i64> {
+        self.config.as_ref()?.get_i64(key).ok()
+    }
+
+    /// Boolean value, accepts git's `true`/`yes`/`on`/`1` spellings
+    pub(crate) fn get_bool(&self, key: &str) -> Option<bool> {
+        self.config.as_ref()?.get_bool(key).ok()
+    }
+
+    pub(crate) fn get_f64(&self, key: &str) -> Option<f64> {
+        self.get_string(key)?.trim().parse().ok()
+    }
+
+    /// All `(key, value)` pairs under `section`, with the `section.` prefix stripped
+    pub(crate) fn get_section(&self, section: &str) -> Vec<(String, String)> {
+        let mut values = Vec::new();
+        let prefix = format!("{}.", section.to_lowercase());
+        if let Some(config) = self.config.as_ref() {
+            if let Ok(entries) = config.entries(None) {
+                let _ = entries.for_each(|entry| {
+                    if let (Some(name), Some(value)) = (entry.name(), entry.value()) {
+                        if let Some(key) = name.strip_prefix(&prefix) {
+                            values.push((key.to_owned(), value.to_owned()));
+                        }
+                    }
+                });
+            }
+        }
+        values
+    }
+}
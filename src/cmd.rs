@@ -19,6 +19,10 @@ pub struct Cli {
     /// Generate Shell Completions
     #[arg(long = "completions", value_enum)]
     completions: Option<Shell>,
+
+    /// Why rules asking for confirmation may be overridden, e.g. changes to paths owned by others
+    #[arg(long = "reason", global = true)]
+    pub(crate) reason: Option<String>,
 }
 
 #[derive(Debug, Subcommand)]
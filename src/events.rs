@@ -9,7 +9,7 @@ use crate::{
     util::find_hook_with_extension,
 };
 pub mod git_add;
-mod git_branch;
+pub(crate) mod git_branch;
 mod git_checkout;
 mod git_clean;
 pub mod git_clone;
@@ -43,7 +43,6 @@ pub(crate) trait AtomicEvent {
 
     #[allow(unused)]
     fn get_action_description(&self) -> &str;
-    fn add_pre_check_rule(&mut self, rule: Box<dyn Rule + Send + Sync>);
     fn get_pre_check_rule(&self) -> &Vec<Box<dyn Rule + Send + Sync>>;
     // Plain execute the event, without any checks and hook
     fn raw_execute(&self) -> Result<bool, Box<BGitError>>;
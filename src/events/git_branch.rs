@@ -1,5 +1,6 @@
 use super::AtomicEvent;
 use crate::bgit_error::{BGitError, BGitErrorWorkflowType, NO_EVENT, NO_RULE, NO_STEP};
+use crate::rules::a23_branch_naming::BranchNamingRule;
 use crate::rules::Rule;
 use git2::{Repository, Branch, BranchType};
 
@@ -47,10 +48,6 @@ impl AtomicEvent for GitBranch {
         "Manage Git branches"
     }
 
-    fn add_pre_check_rule(&mut self, rule: Box<dyn Rule + Send + Sync>) {
-        self.pre_check_rules.push(rule);
-    }
-
     fn get_pre_check_rule(&self) -> &Vec<Box<dyn Rule + Send + Sync>> {
         &self.pre_check_rules
     }
@@ -153,6 +150,15 @@ impl AtomicEvent for GitBranch {
                 )));
             }
 
+            // Enforced here rather than as a pre-check rule, it needs the name being created
+            let naming_rule = BranchNamingRule::for_new_branch(&self.branch_name);
+            if !naming_rule.execute()? {
+                return Ok(false);
+            }
+            let branch_name = naming_rule
+                .new_branch_name()
+                .unwrap_or_else(|| self.branch_name.clone());
+
             let head = repo.head().map_err(|e| {
                 Box::new(BGitError::new(
                     "Branch Error",
@@ -175,7 +181,7 @@ impl AtomicEvent for GitBranch {
                 ))
             })?;
 
-            repo.branch(&self.branch_name, &commit, false).map_err(|e| {
+            repo.branch(&branch_name, &commit, false).map_err(|e| {
                 Box::new(BGitError::new(
                     "Branch Error",
                     &format!("Failed to create branch: {}", e),
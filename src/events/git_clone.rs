@@ -29,10 +29,6 @@ impl AtomicEvent for GitClone {
         "Clone a Git repository"
     }
 
-    fn add_pre_check_rule(&mut self, rule: Box<dyn Rule + Send + Sync>) {
-        self.pre_check_rules.push(rule);
-    }
-
     fn get_pre_check_rule(&self) -> &Vec<Box<dyn Rule + Send + Sync>> {
         &self.pre_check_rules
     }
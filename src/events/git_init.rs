@@ -1,7 +1,7 @@
 use super::AtomicEvent;
 use crate::{
     bgit_error::{BGitError, BGitErrorWorkflowType, NO_RULE, NO_STEP},
-    rules::Rule,
+    rules::{a10_git_default_config::GitDefaultConfigRule, Rule},
 };
 use git2::{Repository, RepositoryInitOptions};
 use std::{env, path::Path};
@@ -41,7 +41,7 @@ impl AtomicEvent for GitInit {
     {
         GitInit {
             name: "git_init".to_owned(),
-            pre_check_rules: vec![],
+            pre_check_rules: vec![Box::new(GitDefaultConfigRule::new())],
             path: ".".to_owned(), // Default to current directory
         }
     }
@@ -54,10 +54,6 @@ impl AtomicEvent for GitInit {
         "Initialize git repository"
     }
 
-    fn add_pre_check_rule(&mut self, rule: Box<dyn Rule + Send + Sync>) {
-        self.pre_check_rules.push(rule);
-    }
-
     fn get_pre_check_rule(&self) -> &Vec<Box<dyn Rule + Send + Sync>> {
         &self.pre_check_rules
     }
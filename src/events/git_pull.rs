@@ -32,10 +32,6 @@ impl AtomicEvent for GitPull {
         "Pull latest changes from remote"
     }
 
-    fn add_pre_check_rule(&mut self, rule: Box<dyn Rule + Send + Sync>) {
-        self.pre_check_rules.push(rule);
-    }
-
     fn get_pre_check_rule(&self) -> &Vec<Box<dyn Rule + Send + Sync>> {
         &self.pre_check_rules
     }
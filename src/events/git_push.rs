@@ -1 +1,173 @@
+use super::AtomicEvent;
+use crate::bgit_error::{BGitError, BGitErrorWorkflowType, NO_RULE, NO_STEP};
+use crate::remote_url::RemoteUrl;
+use crate::rules::a01_git_install::IsGitInstalledLocally;
+use crate::rules::a03_github_username::GitHubUsernameRule;
+use crate::rules::a04_gitlab_username::GitLabUsernameRule;
+use crate::rules::a05_github_credentials_http::GitHubCredentialsHttpRule;
+use crate::rules::a06_gitlab_credentials_http::GitLabCredentialsHttpRule;
+use crate::rules::a07_github_credentials_ssh::{identity_files, GitHubCredentialsSshRule};
+use crate::rules::a08_gitlab_credentials_ssh::GitLabCredentialsSshRule;
+use crate::rules::a11_git_remote_http_ssh::GitRemoteHttpSshRule;
+use crate::rules::a14_big_repo_size::BigRepoSizeRule;
+use crate::rules::a16_protected_branch::ProtectedBranchRule;
+use crate::rules::a22_submodules::SubmoduleRule;
+use crate::rules::a24_outgoing_commits::OutgoingCommitsRule;
+use crate::rules::Rule;
+use crate::ssh_config::SshConfig;
+use git2::{BranchType, Cred, CredentialType, PushOptions, RemoteCallbacks, Repository};
+use std::cell::{Cell, RefCell};
+use std::env;
 
+pub struct GitPush {
+    name: String,
+    pre_check_rules: Vec<Box<dyn Rule + Send + Sync>>,
+}
+
+impl AtomicEvent for GitPush {
+    fn new() -> Self {
+        GitPush {
+            name: String::from("git_push"),
+            pre_check_rules: vec![
+                // Storing credentials and rebasing outgoing commits go through the git CLI
+                Box::new(IsGitInstalledLocally::new()),
+                Box::new(ProtectedBranchRule::new()),
+                Box::new(SubmoduleRule::new()),
+                Box::new(OutgoingCommitsRule::new()),
+                Box::new(BigRepoSizeRule::new()),
+                Box::new(GitRemoteHttpSshRule::new()),
+                Box::new(GitHubUsernameRule::new()),
+                Box::new(GitLabUsernameRule::new()),
+                Box::new(GitHubCredentialsHttpRule::new()),
+                Box::new(GitLabCredentialsHttpRule::new()),
+                Box::new(GitHubCredentialsSshRule::new()),
+                Box::new(GitLabCredentialsSshRule::new()),
+            ],
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_action_description(&self) -> &str {
+        "Push the current branch to its remote"
+    }
+
+    fn get_pre_check_rule(&self) -> &Vec<Box<dyn Rule + Send + Sync>> {
+        &self.pre_check_rules
+    }
+
+    /// Pushes the checked out branch to its upstream, or to a branch of the same name
+    /// on `origin` which then becomes the upstream
+    fn raw_execute(&self) -> Result<bool, Box<BGitError>> {
+        let cwd = env::current_dir().map_err(|e| self.push_error("Failed to get cwd", e))?;
+        let repo = Repository::discover(cwd)
+            .map_err(|e| self.push_error("Failed to open repository", e))?;
+
+        let head = repo
+            .head()
+            .map_err(|e| self.push_error("Failed to get HEAD", e))?;
+        let branch_name = match head.shorthand() {
+            Some(name) if head.is_branch() => name.to_owned(),
+            _ => {
+                return Err(self.push_error("Push Error", "HEAD is not on a branch"));
+            }
+        };
+
+        let config = repo
+            .config()
+            .map_err(|e| self.push_error("Failed to read git config", e))?;
+        let remote_name = config
+            .get_string(&format!("branch.{}.remote", branch_name))
+            .unwrap_or_else(|_| String::from("origin"));
+        let remote_ref = config
+            .get_string(&format!("branch.{}.merge", branch_name))
+            .unwrap_or_else(|_| format!("refs/heads/{}", branch_name));
+        let mut remote = repo
+            .find_remote(&remote_name)
+            .map_err(|e| self.push_error("Failed to find remote", e))?;
+
+        let rejection = RefCell::new(None);
+        // libgit2 calls back again after every rejected credential, each source is tried once.
+        // For SSH these are the agent, then the key files the SSH credential rules accept
+        let ssh_attempts = Cell::new(0);
+        let credential_helper_tried = Cell::new(false);
+        let mut callbacks = RemoteCallbacks::new();
+        callbacks.credentials(|url, username_from_url, allowed_types| {
+            if allowed_types.contains(CredentialType::SSH_KEY) {
+                let username = username_from_url.unwrap_or("git");
+                let attempt = ssh_attempts.replace(ssh_attempts.get() + 1);
+                let agent_attempts = usize::from(env::var_os("SSH_AUTH_SOCK").is_some());
+                if attempt < agent_attempts {
+                    return Cred::ssh_key_from_agent(username);
+                }
+                let key_files = RemoteUrl::parse(url)
+                    .map(|remote_url| identity_files(SshConfig::load().as_ref(), &remote_url.host))
+                    .unwrap_or_default();
+                match key_files.get(attempt - agent_attempts) {
+                    // Keys with a passphrase only work through the agent
+                    Some(key_file) => Cred::ssh_key(username, None, key_file, None),
+                    None => Err(git2::Error::from_str(
+                        "Neither the SSH agent nor the keys in ~/.ssh are accepted by the remote, \
+                        add your key to the remote account or load it with ssh-add",
+                    )),
+                }
+            } else {
+                if credential_helper_tried.replace(true) {
+                    return Err(git2::Error::from_str(
+                        "The credential helper has no credential the remote accepts",
+                    ));
+                }
+                Cred::credential_helper(&config, url, username_from_url)
+            }
+        });
+        callbacks.push_update_reference(|reference, status| {
+            if let Some(message) = status {
+                *rejection.borrow_mut() = Some(format!("{} rejected: {}", reference, message));
+            }
+            Ok(())
+        });
+        let mut push_options = PushOptions::new();
+        push_options.remote_callbacks(callbacks);
+
+        remote
+            .push(
+                &[format!("refs/heads/{}:{}", branch_name, remote_ref)],
+                Some(&mut push_options),
+            )
+            .map_err(|e| self.push_error("Failed to push", e))?;
+        if let Some(rejection) = rejection.borrow_mut().take() {
+            return Err(self.push_error("Push rejected", rejection));
+        }
+
+        let mut branch = repo
+            .find_branch(&branch_name, BranchType::Local)
+            .map_err(|e| self.push_error("Failed to find branch", e))?;
+        if branch.upstream().is_err() {
+            let upstream = format!(
+                "{}/{}",
+                remote_name,
+                remote_ref.trim_start_matches("refs/heads/")
+            );
+            branch
+                .set_upstream(Some(&upstream))
+                .map_err(|e| self.push_error("Failed to set upstream", e))?;
+        }
+
+        Ok(true)
+    }
+}
+
+impl GitPush {
+    fn push_error(&self, name: &str, error: impl std::fmt::Display) -> Box<BGitError> {
+        Box::new(BGitError::new(
+            name,
+            &error.to_string(),
+            BGitErrorWorkflowType::AtomicEvent,
+            NO_STEP,
+            self.get_name(),
+            NO_RULE,
+        ))
+    }
+}
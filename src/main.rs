@@ -4,12 +4,18 @@ use crate::cmd::init::init;
 use crate::cmd::log::log;
 use crate::cmd::{Cli, Commands};
 
+mod bgit_config;
 mod bgit_error;
 mod cmd;
 mod events;
 mod hook_executor;
+mod remote_url;
 mod rules;
+mod signing;
+mod ssh_config;
 mod step;
+#[cfg(test)]
+mod test_util;
 mod util;
 mod workflow_queue;
 mod workflows;
@@ -18,6 +24,9 @@ fn main() {
     let cli_instance_wrap = Cli::new();
 
     if let Some(cli_instance) = cli_instance_wrap {
+        if let Some(reason) = &cli_instance.reason {
+            rules::set_override_reason(reason);
+        }
         match cli_instance.command {
             Some(Commands::Log) => log(),
             Some(Commands::Init) => init(),
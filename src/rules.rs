@@ -1,19 +1,43 @@
 use crate::bgit_error::{BGitError, BGitErrorWorkflowType, NO_EVENT, NO_STEP};
+use std::sync::OnceLock;
 
 pub(crate) mod a01_git_install;
-mod a02_git_name_email_setup;
-mod a03_github_username;
-mod a04_gitlab_username;
-mod a05_github_credentials_http;
-mod a06_gitlab_credentials_http;
-mod a07_github_credentials_ssh;
-mod a08_gitlab_credentials_ssh;
-mod a09_commit_gpg_sign;
-mod a11_git_remote_http_ssh;
-mod a12_no_secrets_staged;
-mod a13_git_lfs;
-mod a14_big_repo_size;
-mod a15_file_not_gitignored;
+pub(crate) mod a02_git_name_email_setup;
+pub(crate) mod a03_github_username;
+pub(crate) mod a04_gitlab_username;
+pub(crate) mod a05_github_credentials_http;
+pub(crate) mod a06_gitlab_credentials_http;
+pub(crate) mod a07_github_credentials_ssh;
+pub(crate) mod a08_gitlab_credentials_ssh;
+pub(crate) mod a09_commit_gpg_sign;
+pub(crate) mod a10_git_default_config;
+pub(crate) mod a11_git_remote_http_ssh;
+pub(crate) mod a12_no_secrets_staged;
+pub(crate) mod a13_git_lfs;
+pub(crate) mod a14_big_repo_size;
+pub(crate) mod a15_file_not_gitignored;
+pub(crate) mod a16_protected_branch;
+pub(crate) mod a17_conventional_commit_message;
+pub(crate) mod a18_codeowners;
+pub(crate) mod a19_conflict_markers;
+pub(crate) mod a20_line_endings;
+pub(crate) mod a21_path_safety;
+pub(crate) mod a22_submodules;
+pub(crate) mod a23_branch_naming;
+pub(crate) mod a24_outgoing_commits;
+pub(crate) mod a25_behind_upstream;
+pub(crate) mod a26_change_size;
+
+/// Set from `--reason`, lets rules that ask for confirmation continue without a prompt
+static OVERRIDE_REASON: OnceLock<String> = OnceLock::new();
+
+pub(crate) fn set_override_reason(reason: &str) {
+    let _ = OVERRIDE_REASON.set(reason.to_owned());
+}
+
+pub(crate) fn override_reason() -> Option<&'static str> {
+    OVERRIDE_REASON.get().map(String::as_str)
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) enum RuleLevel {
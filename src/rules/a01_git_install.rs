@@ -1,7 +1,63 @@
 use crate::bgit_error::{BGitError, BGitErrorWorkflowType, NO_EVENT, NO_STEP};
 use crate::rules::{Rule, RuleLevel, RuleOutput};
+use crate::util::program_exists;
+use dialoguer::Confirm;
+use std::fs;
 use std::process::Command;
 
+/// Oldest git bgit supports, `init.defaultBranch` needs 2.28
+const MIN_GIT_VERSION: (u32, u32, u32) = (2, 28, 0);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PackageManager {
+    Apt,
+    Dnf,
+    Pacman,
+    Apk,
+    Zypper,
+    Brew,
+}
+
+impl PackageManager {
+    /// Probed in this order when `/etc/os-release` doesn't settle it
+    const ALL: [PackageManager; 6] = [
+        PackageManager::Apt,
+        PackageManager::Dnf,
+        PackageManager::Pacman,
+        PackageManager::Apk,
+        PackageManager::Zypper,
+        PackageManager::Brew,
+    ];
+
+    fn program(&self) -> &'static str {
+        match self {
+            PackageManager::Apt => "apt-get",
+            PackageManager::Dnf => "dnf",
+            PackageManager::Pacman => "pacman",
+            PackageManager::Apk => "apk",
+            PackageManager::Zypper => "zypper",
+            PackageManager::Brew => "brew",
+        }
+    }
+
+    /// Command that installs or upgrades git, without the sudo prefix
+    fn install_args(&self) -> &'static [&'static str] {
+        match self {
+            PackageManager::Apt => &["apt-get", "install", "-y", "git"],
+            PackageManager::Dnf => &["dnf", "install", "-y", "git"],
+            PackageManager::Pacman => &["pacman", "-S", "--needed", "--noconfirm", "git"],
+            PackageManager::Apk => &["apk", "add", "git"],
+            PackageManager::Zypper => &["zypper", "--non-interactive", "install", "git"],
+            PackageManager::Brew => &["brew", "install", "git"],
+        }
+    }
+
+    /// Homebrew refuses to run as root
+    fn needs_root(&self) -> bool {
+        *self != PackageManager::Brew
+    }
+}
+
 pub(crate) struct IsGitInstalledLocally {
     name: String,
     description: String,
@@ -33,43 +89,222 @@ impl Rule for IsGitInstalledLocally {
         let output = Command::new("git").arg("--version").output();
         match output {
             Err(e) => Ok(RuleOutput::Exception(format!(
-                "Failed to execute command: {}",
+                "Git is not installed, failed to execute git: {}",
                 e
             ))),
             Ok(output_response) => {
-                if output_response.status.success() {
-                    Ok(RuleOutput::Success)
-                } else {
-                    Ok(RuleOutput::Exception("Git is not installed".to_string()))
+                if !output_response.status.success() {
+                    return Ok(RuleOutput::Exception("Git is not installed".to_string()));
+                }
+                let version_output = String::from_utf8_lossy(&output_response.stdout);
+                match Self::parse_git_version(&version_output) {
+                    Some(version) if version >= MIN_GIT_VERSION => {
+                        println!("Using git {}", Self::format_version(version));
+                        Ok(RuleOutput::Success)
+                    }
+                    Some(version) => Ok(RuleOutput::Exception(format!(
+                        "Git {} is installed, but bgit needs at least git {}",
+                        Self::format_version(version),
+                        Self::format_version(MIN_GIT_VERSION)
+                    ))),
+                    None => Ok(RuleOutput::Exception(format!(
+                        "Could not determine the installed git version from '{}'",
+                        version_output.trim()
+                    ))),
                 }
             }
         }
     }
 
     fn try_fix(&self) -> Result<bool, Box<BGitError>> {
-        println!("Executing sudo apt-get install git");
-        let output = Command::new("sudo")
-            .arg("apt-get")
-            .arg("install")
-            .arg("git")
-            .output();
+        let os_release = fs::read_to_string("/etc/os-release").unwrap_or_default();
+        if Self::os_release_ids(&os_release).contains(&String::from("nixos")) {
+            println!(
+                "On NixOS, add git to environment.systemPackages in configuration.nix \
+                or run: nix-env -iA nixos.git"
+            );
+            return Ok(false);
+        }
 
-        match output {
-            Err(e) => Err(Box::new(BGitError::new(
-                "Failed to execute command",
-                &e.to_string(),
-                BGitErrorWorkflowType::Rules,
-                NO_STEP,
-                NO_EVENT,
-                self.get_name(),
-            ))),
-            Ok(output_response) => {
-                if output_response.status.success() {
-                    Ok(true)
-                } else {
-                    Ok(false)
+        let package_manager = match Self::detect_package_manager(&os_release) {
+            Some(package_manager) => package_manager,
+            None if cfg!(target_os = "macos") => {
+                println!(
+                    "Install git with the Xcode command line tools: xcode-select --install\n\
+                    or install Homebrew from https://brew.sh and run: brew install git"
+                );
+                return Ok(false);
+            }
+            None => {
+                println!(
+                    "Could not detect a supported package manager. \
+                    Please install git from https://git-scm.com/downloads"
+                );
+                return Ok(false);
+            }
+        };
+
+        let mut command_line: Vec<&str> = Vec::new();
+        if package_manager.needs_root() && !Self::is_root() {
+            match ["sudo", "doas"]
+                .into_iter()
+                .find(|program| program_exists(program))
+            {
+                Some(escalation) => command_line.push(escalation),
+                None => {
+                    println!(
+                        "Neither sudo nor doas is available. Run this as root to install git:\n  {}",
+                        package_manager.install_args().join(" ")
+                    );
+                    return Ok(false);
                 }
             }
         }
+        command_line.extend(package_manager.install_args());
+
+        let confirmed = Confirm::new()
+            .with_prompt(format!(
+                "Install git by running `{}`?",
+                command_line.join(" ")
+            ))
+            .default(true)
+            .interact()
+            .map_err(|e| self.rule_error("Input Error", e))?;
+        if !confirmed {
+            return Ok(false);
+        }
+
+        // Inherit the terminal, sudo may ask for a password
+        let status = Command::new(command_line[0])
+            .args(&command_line[1..])
+            .status()
+            .map_err(|e| self.rule_error("Failed to execute command", e))?;
+        Ok(status.success())
+    }
+}
+
+impl IsGitInstalledLocally {
+    /// `git version 2.43.0`, `git version 2.39.3 (Apple Git-145)`, `git version 2.45.1.windows.1`
+    fn parse_git_version(output: &str) -> Option<(u32, u32, u32)> {
+        let version = output.trim().strip_prefix("git version ")?;
+        let mut parts = version
+            .split(|c: char| c == '.' || c.is_whitespace())
+            .map(|part| part.parse::<u32>());
+        let major = parts.next()?.ok()?;
+        let minor = parts.next()?.ok()?;
+        let patch = parts.next().and_then(|part| part.ok()).unwrap_or(0);
+        Some((major, minor, patch))
+    }
+
+    fn format_version((major, minor, patch): (u32, u32, u32)) -> String {
+        format!("{}.{}.{}", major, minor, patch)
+    }
+
+    /// `ID` followed by the `ID_LIKE` entries of `/etc/os-release`
+    fn os_release_ids(os_release: &str) -> Vec<String> {
+        let value = |key: &str| {
+            os_release
+                .lines()
+                .find_map(|line| line.strip_prefix(key))
+                .map(|value| value.trim().trim_matches('"').to_lowercase())
+                .unwrap_or_default()
+        };
+        let mut ids = vec![value("ID=")];
+        ids.extend(value("ID_LIKE=").split_whitespace().map(String::from));
+        ids.retain(|id| !id.is_empty());
+        ids
+    }
+
+    fn package_manager_for_distro(id: &str) -> Option<PackageManager> {
+        match id {
+            "debian" | "ubuntu" | "linuxmint" | "pop" | "raspbian" => Some(PackageManager::Apt),
+            "fedora" | "rhel" | "centos" | "rocky" | "almalinux" => Some(PackageManager::Dnf),
+            "arch" | "manjaro" | "endeavouros" => Some(PackageManager::Pacman),
+            "alpine" => Some(PackageManager::Apk),
+            "opensuse" | "opensuse-leap" | "opensuse-tumbleweed" | "suse" | "sles" => {
+                Some(PackageManager::Zypper)
+            }
+            _ => None,
+        }
+    }
+
+    fn detect_package_manager(os_release: &str) -> Option<PackageManager> {
+        Self::os_release_ids(os_release)
+            .iter()
+            .find_map(|id| Self::package_manager_for_distro(id))
+            .filter(|package_manager| program_exists(package_manager.program()))
+            .or_else(|| {
+                PackageManager::ALL
+                    .into_iter()
+                    .find(|package_manager| program_exists(package_manager.program()))
+            })
+    }
+
+    fn is_root() -> bool {
+        Command::new("id")
+            .arg("-u")
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim() == "0")
+            .unwrap_or(false)
+    }
+
+    fn rule_error(&self, name: &str, error: impl std::fmt::Display) -> Box<BGitError> {
+        Box::new(BGitError::new(
+            name,
+            &error.to_string(),
+            BGitErrorWorkflowType::Rules,
+            NO_STEP,
+            NO_EVENT,
+            self.get_name(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_git_version() {
+        assert_eq!(
+            IsGitInstalledLocally::parse_git_version("git version 2.43.0\n"),
+            Some((2, 43, 0))
+        );
+        assert_eq!(
+            IsGitInstalledLocally::parse_git_version("git version 2.39.3 (Apple Git-145)"),
+            Some((2, 39, 3))
+        );
+        assert_eq!(
+            IsGitInstalledLocally::parse_git_version("git version 2.45.1.windows.1"),
+            Some((2, 45, 1))
+        );
+        assert_eq!(IsGitInstalledLocally::parse_git_version("hub 2.14"), None);
+        assert!((2, 17, 1) < MIN_GIT_VERSION);
+    }
+
+    #[test]
+    fn test_package_manager_from_os_release() {
+        let fedora = "NAME=\"Fedora Linux\"\nID=fedora\nVERSION_ID=40\n";
+        let ids = IsGitInstalledLocally::os_release_ids(fedora);
+        assert_eq!(ids, vec![String::from("fedora")]);
+
+        let mint = "ID=linuxmint\nID_LIKE=\"ubuntu debian\"\n";
+        assert_eq!(
+            IsGitInstalledLocally::os_release_ids(mint),
+            vec!["linuxmint", "ubuntu", "debian"]
+        );
+        assert_eq!(
+            IsGitInstalledLocally::package_manager_for_distro("rocky"),
+            Some(PackageManager::Dnf)
+        );
+        assert_eq!(
+            IsGitInstalledLocally::package_manager_for_distro("alpine"),
+            Some(PackageManager::Apk)
+        );
+        assert_eq!(
+            IsGitInstalledLocally::package_manager_for_distro("nixos"),
+            None
+        );
+        assert!(!PackageManager::Brew.needs_root());
     }
 }
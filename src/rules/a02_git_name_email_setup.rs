@@ -1,6 +1,9 @@
-use crate::bgit_error::BGitError;
+use crate::bgit_error::{BGitError, BGitErrorWorkflowType, NO_EVENT, NO_STEP};
 use crate::rules::{Rule, RuleLevel, RuleOutput};
-use std::process::Command;
+use dialoguer::{theme::ColorfulTheme, Input, Select};
+use git2::{Config, ConfigLevel, Repository};
+use std::env;
+use std::path::{Path, PathBuf};
 
 pub(crate) struct GitNameEmailSetupRule {
     name: String,
@@ -30,49 +33,15 @@ impl Rule for GitNameEmailSetupRule {
     }
 
     fn check(&self) -> Result<RuleOutput, Box<BGitError>> {
-        // Check git user.name
-        let name_output = Command::new("git")
-            .args(["config", "--get", "user.name"])
-            .output()
-            .map_err(|e| {
-                Box::new(BGitError::new(
-                    "Failed to execute git command",
-                    &e.to_string(),
-                    crate::bgit_error::BGitErrorWorkflowType::Rules,
-                    crate::bgit_error::NO_STEP,
-                    crate::bgit_error::NO_EVENT,
-                    self.get_name(),
-                ))
-            })?;
-
-        // Check git user.email
-        let email_output = Command::new("git")
-            .args(["config", "--get", "user.email"])
-            .output()
-            .map_err(|e| {
-                Box::new(BGitError::new(
-                    "Failed to execute git command",
-                    &e.to_string(),
-                    crate::bgit_error::BGitErrorWorkflowType::Rules,
-                    crate::bgit_error::NO_STEP,
-                    crate::bgit_error::NO_EVENT,
-                    self.get_name(),
-                ))
-            })?;
-
-        let name = String::from_utf8_lossy(&name_output.stdout)
-            .trim()
-            .to_string();
-        let email = String::from_utf8_lossy(&email_output.stdout)
-            .trim()
-            .to_string();
+        let config = self.open_config()?;
+        let (name, email) = Self::identity(&config);
 
         if name.is_empty() || email.is_empty() {
-            let error_msg = format!(
+            let error_msg = String::from(
                 "Git user.name and/or user.email is not configured.\n\
                 Run:\n\
                   git config --global user.name \"Your Name\"\n\
-                  git config --global user.email \"you@example.com\""
+                  git config --global user.email \"you@example.com\"",
             );
             Ok(RuleOutput::Exception(error_msg))
         } else {
@@ -81,15 +50,183 @@ impl Rule for GitNameEmailSetupRule {
     }
 
     fn try_fix(&self) -> Result<bool, Box<BGitError>> {
-        // Cannot auto-fix as it requires user input
-        // Return false to indicate manual intervention is needed
-        Ok(false)
+        let repo = Self::discover_repo();
+        let (current_name, current_email) = Self::identity(&self.open_config()?);
+        let theme = ColorfulTheme::default();
+
+        let mut name_input = Input::<String>::with_theme(&theme).with_prompt("Your name");
+        if !current_name.is_empty() {
+            name_input = name_input.default(current_name);
+        }
+        let name = name_input
+            .interact_text()
+            .map_err(|e| self.rule_error("Input Error", e))?;
+
+        let mut email_input = Input::<String>::with_theme(&theme)
+            .with_prompt("Your email")
+            .validate_with(|email: &String| -> Result<(), &str> {
+                match Self::is_valid_email(email) {
+                    true => Ok(()),
+                    false => Err("Please enter a valid email address, e.g. you@example.com"),
+                }
+            });
+        if !current_email.is_empty() {
+            email_input = email_input.default(current_email);
+        }
+        let email = email_input
+            .interact_text()
+            .map_err(|e| self.rule_error("Input Error", e))?;
+
+        let mut scopes = vec![
+            "Global, for all repositories",
+            "Directory profile, for all repositories under a folder (e.g. ~/work/)",
+        ];
+        if repo.is_some() {
+            scopes.insert(0, "This repository only");
+        }
+        let selection = Select::with_theme(&theme)
+            .with_prompt("Where should this identity be used?")
+            .default(0)
+            .items(&scopes)
+            .interact()
+            .map_err(|e| self.rule_error("Input Error", e))?;
+
+        match (scopes[selection], &repo) {
+            ("This repository only", Some(repo)) => repo
+                .config()
+                .and_then(|config| config.open_level(ConfigLevel::Local))
+                .and_then(|mut config| Self::set_identity(&mut config, &name, &email))
+                .map_err(|e| self.rule_error("Failed to write git config", e))?,
+            ("Global, for all repositories", _) => self
+                .open_global_config()
+                .and_then(|mut config| Self::set_identity(&mut config, &name, &email))
+                .map_err(|e| self.rule_error("Failed to write git config", e))?,
+            _ => {
+                let directory: String = Input::with_theme(&theme)
+                    .with_prompt("Folder that holds the repositories for this identity")
+                    .default(String::from("~/work/"))
+                    .interact_text()
+                    .map_err(|e| self.rule_error("Input Error", e))?;
+                let home = env::var_os("HOME").map(PathBuf::from).ok_or_else(|| {
+                    self.rule_error("Failed to create profile", "HOME is not set")
+                })?;
+                let mut global_config = self
+                    .open_global_config()
+                    .map_err(|e| self.rule_error("Failed to open git config", e))?;
+                let profile_path =
+                    Self::write_profile(&mut global_config, &home, &directory, &name, &email)
+                        .map_err(|e| self.rule_error("Failed to create profile", e))?;
+                println!(
+                    "Created {}, it is used for repositories under {}",
+                    profile_path.display(),
+                    directory
+                );
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+impl GitNameEmailSetupRule {
+    /// Repository config when inside a repository, so local overrides count, the
+    /// global and system config otherwise
+    fn open_config(&self) -> Result<Config, Box<BGitError>> {
+        match Self::discover_repo() {
+            Some(repo) => repo.config(),
+            None => Config::open_default(),
+        }
+        .map_err(|e| self.rule_error("Failed to open git config", e))
+    }
+
+    fn discover_repo() -> Option<Repository> {
+        env::current_dir()
+            .ok()
+            .and_then(|cwd| Repository::discover(cwd).ok())
+    }
+
+    fn open_global_config(&self) -> Result<Config, git2::Error> {
+        Config::open_default()?.open_global()
+    }
+
+    fn set_identity(config: &mut Config, name: &str, email: &str) -> Result<(), git2::Error> {
+        config.set_str("user.name", name.trim())?;
+        config.set_str("user.email", email.trim())
+    }
+
+    /// Minimal sanity check: `local@domain.tld` without whitespace
+    fn is_valid_email(email: &str) -> bool {
+        let email = email.trim();
+        let (local, domain) = match email.split_once('@') {
+            Some(parts) => parts,
+            None => return false,
+        };
+        !local.is_empty()
+            && !email.chars().any(char::is_whitespace)
+            && !domain.contains('@')
+            && domain.contains('.')
+            && domain.split('.').all(|label| !label.is_empty())
+    }
+
+    /// Writes the identity to `~/.gitconfig-<folder name>` and includes it from the
+    /// global config with `[includeIf "gitdir:<directory>"]`
+    fn write_profile(
+        global_config: &mut Config,
+        home: &Path,
+        directory: &str,
+        name: &str,
+        email: &str,
+    ) -> Result<PathBuf, git2::Error> {
+        // Without the trailing slash gitdir only matches the folder itself
+        let mut gitdir = directory.trim().to_string();
+        if !gitdir.ends_with('/') {
+            gitdir.push('/');
+        }
+        let profile_name = Path::new(gitdir.trim_end_matches('/'))
+            .file_name()
+            .map(|folder| folder.to_string_lossy().to_string())
+            .unwrap_or_else(|| String::from("profile"));
+        let file_name = format!(".gitconfig-{}", profile_name);
+
+        let profile_path = home.join(&file_name);
+        let mut profile = Config::open(&profile_path)?;
+        Self::set_identity(&mut profile, name, email)?;
+
+        global_config.set_str(
+            &format!("includeIf.gitdir:{}.path", gitdir),
+            &format!("~/{}", file_name),
+        )?;
+        Ok(profile_path)
+    }
+
+    /// Trimmed `user.name` and `user.email`, empty when unset
+    fn identity(config: &Config) -> (String, String) {
+        let value = |key: &str| {
+            config
+                .get_string(key)
+                .map(|value| value.trim().to_string())
+                .unwrap_or_default()
+        };
+        (value("user.name"), value("user.email"))
+    }
+
+    fn rule_error(&self, name: &str, error: impl std::fmt::Display) -> Box<BGitError> {
+        Box::new(BGitError::new(
+            name,
+            &error.to_string(),
+            BGitErrorWorkflowType::Rules,
+            NO_STEP,
+            NO_EVENT,
+            self.get_name(),
+        ))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs;
+    use tempfile::TempDir;
 
     #[test]
     fn test_rule_creation() {
@@ -97,4 +234,69 @@ mod tests {
         assert_eq!(rule.get_name(), "RULE_git-name-email-setup");
         assert_eq!(rule.get_level(), RuleLevel::Error);
     }
+
+    #[test]
+    fn test_identity_from_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("gitconfig");
+        fs::write(&config_path, "[user]\n\tname = Test\n").unwrap();
+        let config = Config::open(&config_path).unwrap();
+        assert_eq!(
+            GitNameEmailSetupRule::identity(&config),
+            (String::from("Test"), String::new())
+        );
+
+        fs::write(
+            &config_path,
+            "[user]\n\tname = Test\n\temail = test@example.com \n",
+        )
+        .unwrap();
+        let config = Config::open(&config_path).unwrap();
+        assert_eq!(
+            GitNameEmailSetupRule::identity(&config),
+            (String::from("Test"), String::from("test@example.com"))
+        );
+    }
+
+    #[test]
+    fn test_email_validation() {
+        assert!(GitNameEmailSetupRule::is_valid_email("you@example.com"));
+        assert!(GitNameEmailSetupRule::is_valid_email(
+            "first.last+tag@mail.example.org"
+        ));
+        assert!(!GitNameEmailSetupRule::is_valid_email("you"));
+        assert!(!GitNameEmailSetupRule::is_valid_email("@example.com"));
+        assert!(!GitNameEmailSetupRule::is_valid_email("you@localhost"));
+        assert!(!GitNameEmailSetupRule::is_valid_email("you@example..com"));
+        assert!(!GitNameEmailSetupRule::is_valid_email("y ou@example.com"));
+    }
+
+    #[test]
+    fn test_write_profile() {
+        let temp_dir = TempDir::new().unwrap();
+        let global_path = temp_dir.path().join(".gitconfig");
+        let mut global_config = Config::open(&global_path).unwrap();
+
+        let profile_path = GitNameEmailSetupRule::write_profile(
+            &mut global_config,
+            temp_dir.path(),
+            "~/work",
+            "Work Name",
+            "me@company.example",
+        )
+        .unwrap();
+        assert_eq!(profile_path, temp_dir.path().join(".gitconfig-work"));
+
+        let profile = Config::open(&profile_path).unwrap();
+        assert_eq!(
+            GitNameEmailSetupRule::identity(&profile),
+            (
+                String::from("Work Name"),
+                String::from("me@company.example")
+            )
+        );
+        let global_content = fs::read_to_string(&global_path).unwrap();
+        assert!(global_content.contains("[includeIf \"gitdir:~/work/\"]"));
+        assert!(global_content.contains("path = ~/.gitconfig-work"));
+    }
 }
@@ -1 +1,393 @@
+use crate::bgit_error::{BGitError, BGitErrorWorkflowType, NO_EVENT, NO_STEP};
+use crate::remote_url::{GitHosting, RemoteUrl};
+use crate::rules::{Rule, RuleLevel, RuleOutput};
+use crate::ssh_config::SshConfig;
+use dialoguer::{theme::ColorfulTheme, Input};
+use git2::{Config, Repository};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
 
+pub(crate) struct GitHubUsernameRule {
+    name: String,
+    description: String,
+    level: RuleLevel,
+}
+
+impl Rule for GitHubUsernameRule {
+    fn new() -> Self {
+        GitHubUsernameRule {
+            name: String::from("RULE_github-username-setup"),
+            description: String::from(
+                "Ensure the GitHub account you are authenticated as owns the origin repository",
+            ),
+            level: RuleLevel::Warning,
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_description(&self) -> &str {
+        &self.description
+    }
+
+    fn get_level(&self) -> RuleLevel {
+        self.level.clone()
+    }
+
+    fn check(&self) -> Result<RuleOutput, Box<BGitError>> {
+        check_host_username(self.get_name(), GitHosting::GitHub)
+    }
+
+    fn try_fix(&self) -> Result<bool, Box<BGitError>> {
+        fix_host_username(self.get_name(), GitHosting::GitHub)
+    }
+}
+
+/// Username for a hosting service and where it was found
+#[derive(Debug, PartialEq)]
+struct HostIdentity {
+    username: String,
+    source: String,
+}
+
+/// Shared by the GitHub and GitLab username rules
+pub(super) fn check_host_username(
+    rule_name: &str,
+    hosting: GitHosting,
+) -> Result<RuleOutput, Box<BGitError>> {
+    let repo = open_repo(rule_name)?;
+    let (host, owner) = match origin_owner(&repo, hosting) {
+        Some(origin) => origin,
+        // Not hosted on this service, nothing to compare against
+        None => return Ok(RuleOutput::Success),
+    };
+
+    match resolve_identity(&repo, hosting, &host) {
+        None => Ok(RuleOutput::Exception(format!(
+            "No {} username is configured for {}.\n\
+            Run:\n\
+              git config --global {} \"<your-{}-username>\"",
+            hosting.display_name(),
+            host,
+            username_config_key(hosting),
+            hosting.display_name().to_lowercase()
+        ))),
+        Some(identity) if !identity.username.eq_ignore_ascii_case(&owner) => {
+            Ok(RuleOutput::Exception(format!(
+                "origin ({}/{}) is owned by '{}', but you are '{}' (from {}).\n\
+                Make sure you are pushing to your own fork or to an organization you belong to.",
+                host, owner, owner, identity.username, identity.source
+            )))
+        }
+        Some(_) => Ok(RuleOutput::Success),
+    }
+}
+
+pub(super) fn fix_host_username(
+    rule_name: &str,
+    hosting: GitHosting,
+) -> Result<bool, Box<BGitError>> {
+    let repo = open_repo(rule_name)?;
+    let host = match origin_owner(&repo, hosting) {
+        Some((host, _)) => host,
+        None => return Ok(true),
+    };
+
+    // The origin owner is often an organization, so only a known identity is suggested
+    let theme = ColorfulTheme::default();
+    let mut input = Input::<String>::with_theme(&theme)
+        .with_prompt(format!(
+            "Your {} username (leave empty to skip)",
+            hosting.display_name()
+        ))
+        .allow_empty(true);
+    if let Some(identity) = resolve_identity(&repo, hosting, &host) {
+        input = input.default(identity.username);
+    }
+    let username = input
+        .interact_text()
+        .map_err(|e| rule_error(rule_name, "Input Error", e))?;
+    if username.trim().is_empty() {
+        return Ok(true);
+    }
+
+    Config::open_default()
+        .and_then(|mut config| config.open_global())
+        .and_then(|mut config| config.set_str(username_config_key(hosting), username.trim()))
+        .map_err(|e| rule_error(rule_name, "Failed to write git config", e))?;
+
+    Ok(true)
+}
+
+fn open_repo(rule_name: &str) -> Result<Repository, Box<BGitError>> {
+    let cwd = env::current_dir().map_err(|e| rule_error(rule_name, "Failed to get cwd", e))?;
+    Repository::discover(cwd).map_err(|e| rule_error(rule_name, "Failed to open repository", e))
+}
+
+fn username_config_key(hosting: GitHosting) -> &'static str {
+    match hosting {
+        GitHosting::GitHub => "github.user",
+        GitHosting::GitLab => "gitlab.user",
+    }
+}
+
+fn username_env_var(hosting: GitHosting) -> &'static str {
+    match hosting {
+        GitHosting::GitHub => "GITHUB_USER",
+        GitHosting::GitLab => "GITLAB_USER",
+    }
+}
+
+/// `hosts.yml` of the gh CLI or `config.yml` of the glab CLI
+fn cli_config_path(hosting: GitHosting) -> Option<PathBuf> {
+    let (dir_env_var, app_dir, file_name) = match hosting {
+        GitHosting::GitHub => ("GH_CONFIG_DIR", "gh", "hosts.yml"),
+        GitHosting::GitLab => ("GLAB_CONFIG_DIR", "glab-cli", "config.yml"),
+    };
+    if let Some(dir) = env::var_os(dir_env_var) {
+        return Some(PathBuf::from(dir).join(file_name));
+    }
+    if let Some(dir) = env::var_os("XDG_CONFIG_HOME") {
+        return Some(PathBuf::from(dir).join(app_dir).join(file_name));
+    }
+    if cfg!(windows) {
+        let app_dir = match hosting {
+            GitHosting::GitHub => "GitHub CLI",
+            GitHosting::GitLab => "glab-cli",
+        };
+        if let Some(dir) = env::var_os("APPDATA") {
+            return Some(PathBuf::from(dir).join(app_dir).join(file_name));
+        }
+    }
+    env::var_os("HOME").map(|home| {
+        PathBuf::from(home)
+            .join(".config")
+            .join(app_dir)
+            .join(file_name)
+    })
+}
+
+/// Host and owner of `origin`, when it is hosted on `hosting`
+fn origin_owner(repo: &Repository, hosting: GitHosting) -> Option<(String, String)> {
+    let remote = repo.find_remote("origin").ok()?;
+    let url = RemoteUrl::parse(remote.url()?)?;
+    let cli_config = cli_config_path(hosting).and_then(|path| fs::read_to_string(path).ok());
+    let known_host = hosting.matches_host(&url.host)
+        || cli_config
+            .as_deref()
+            .is_some_and(|content| yaml_host_user(content, &url.host).is_some());
+    if !known_host {
+        return None;
+    }
+    let owner = url.owner()?.to_owned();
+    Some((url.host, owner))
+}
+
+/// Account name on `hosting` from local sources, for matching `@user` entries such as
+/// CODEOWNERS owners
+pub(super) fn local_username(repo: &Repository, hosting: GitHosting) -> Option<String> {
+    let host = match origin_owner(repo, hosting) {
+        Some((host, _)) => host,
+        None => match hosting {
+            GitHosting::GitHub => String::from("github.com"),
+            GitHosting::GitLab => String::from("gitlab.com"),
+        },
+    };
+    resolve_identity(repo, hosting, &host).map(|identity| identity.username)
+}
+
+/// Local sources only, no network: env var, git config, gh/glab CLI config,
+/// credential helper usernames and `~/.ssh/config`
+fn resolve_identity(repo: &Repository, hosting: GitHosting, host: &str) -> Option<HostIdentity> {
+    let config = repo.config().ok();
+    resolve_identity_from(
+        env::var(username_env_var(hosting)).ok(),
+        config.as_ref(),
+        hosting,
+        host,
+    )
+}
+
+/// `resolve_identity` with the env var and git config passed in
+fn resolve_identity_from(
+    env_username: Option<String>,
+    config: Option<&Config>,
+    hosting: GitHosting,
+    host: &str,
+) -> Option<HostIdentity> {
+    let identity = |username: String, source: String| {
+        let username = username.trim().to_owned();
+        (!username.is_empty()).then_some(HostIdentity { username, source })
+    };
+
+    if let Some(username) = env_username {
+        if let Some(found) = identity(username, username_env_var(hosting).to_owned()) {
+            return Some(found);
+        }
+    }
+
+    if let Some(config) = config {
+        let config_keys = [
+            username_config_key(hosting).to_owned(),
+            format!("credential.https://{}.username", host),
+        ];
+        for key in config_keys {
+            if let Ok(username) = config.get_string(&key) {
+                if let Some(found) = identity(username, format!("git config {}", key)) {
+                    return Some(found);
+                }
+            }
+        }
+    }
+
+    if let Some(path) = cli_config_path(hosting) {
+        let cli_user = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| yaml_host_user(&content, host));
+        if let Some(found) = cli_user.and_then(|user| identity(user, path.display().to_string())) {
+            return Some(found);
+        }
+    }
+
+    // `git` is the shared SSH user of the hosting services, not an account name
+    let ssh_user = SshConfig::load().and_then(|ssh_config| {
+        ssh_config
+            .options_for(host, "user")
+            .into_iter()
+            .find(|user| *user != "git")
+            .map(String::from)
+    });
+    ssh_user.and_then(|user| identity(user, String::from("~/.ssh/config")))
+}
+
+/// `user` directly below the `<host>:` key, as written by gh (`hosts.yml`)
+/// and glab (`config.yml`, nested under `hosts:`)
+fn yaml_host_user(content: &str, host: &str) -> Option<String> {
+    let host_key = format!("{}:", host);
+    let mut host_indent = None;
+    let mut child_indent = None;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+
+        match host_indent {
+            None => {
+                if trimmed == host_key {
+                    host_indent = Some(indent);
+                }
+            }
+            Some(host_indent) => {
+                if indent <= host_indent {
+                    return None;
+                }
+                let child_indent = *child_indent.get_or_insert(indent);
+                if indent == child_indent {
+                    if let Some(user) = trimmed.strip_prefix("user:") {
+                        return Some(user.trim().trim_matches(['"', '\'']).to_owned());
+                    }
+                }
+            }
+        }
+    }
+
+    None
+}
+
+fn rule_error(rule_name: &str, name: &str, error: impl std::fmt::Display) -> Box<BGitError> {
+    Box::new(BGitError::new(
+        name,
+        &error.to_string(),
+        BGitErrorWorkflowType::Rules,
+        NO_STEP,
+        NO_EVENT,
+        rule_name,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::ConfigLevel;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_rule_creation() {
+        let rule = GitHubUsernameRule::new();
+        assert_eq!(rule.get_name(), "RULE_github-username-setup");
+        assert_eq!(rule.get_level(), RuleLevel::Warning);
+    }
+
+    #[test]
+    fn test_cli_config_user() {
+        let gh_hosts = "github.com:\n    users:\n        alice:\n            oauth_token: x\n    git_protocol: ssh\n    user: alice\n";
+        assert_eq!(
+            yaml_host_user(gh_hosts, "github.com"),
+            Some(String::from("alice"))
+        );
+        assert_eq!(yaml_host_user(gh_hosts, "gitlab.com"), None);
+
+        let glab_config = "git_protocol: ssh\nhosts:\n    gitlab.com:\n        token: x\n        user: \"bob\"\n    gitlab.example.com:\n        user: carol\n";
+        assert_eq!(
+            yaml_host_user(glab_config, "gitlab.com"),
+            Some(String::from("bob"))
+        );
+        assert_eq!(
+            yaml_host_user(glab_config, "gitlab.example.com"),
+            Some(String::from("carol"))
+        );
+    }
+
+    #[test]
+    fn test_identity_from_git_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        repo.remote("origin", "git@github.com:someone-else/repo.git")
+            .unwrap();
+        repo.config()
+            .unwrap()
+            .set_str("credential.https://github.com.username", "alice")
+            .unwrap();
+
+        assert_eq!(
+            origin_owner(&repo, GitHosting::GitHub),
+            Some((String::from("github.com"), String::from("someone-else")))
+        );
+        assert_eq!(origin_owner(&repo, GitHosting::GitLab), None);
+
+        // Only the repository's own config, so the machine's env and global config can't interfere
+        let mut config = Config::new().unwrap();
+        config
+            .add_file(
+                &temp_dir.path().join(".git/config"),
+                ConfigLevel::Local,
+                false,
+            )
+            .unwrap();
+        assert_eq!(
+            resolve_identity_from(None, Some(&config), GitHosting::GitHub, "github.com"),
+            Some(HostIdentity {
+                username: String::from("alice"),
+                source: String::from("git config credential.https://github.com.username"),
+            })
+        );
+        assert_eq!(
+            resolve_identity_from(
+                Some(String::from("bob")),
+                Some(&config),
+                GitHosting::GitHub,
+                "github.com"
+            ),
+            Some(HostIdentity {
+                username: String::from("bob"),
+                source: String::from("GITHUB_USER"),
+            })
+        );
+    }
+}
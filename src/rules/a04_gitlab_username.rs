@@ -1 +1,54 @@
+use crate::bgit_error::BGitError;
+use crate::remote_url::GitHosting;
+use crate::rules::a03_github_username::{check_host_username, fix_host_username};
+use crate::rules::{Rule, RuleLevel, RuleOutput};
 
+pub(crate) struct GitLabUsernameRule {
+    name: String,
+    description: String,
+    level: RuleLevel,
+}
+
+impl Rule for GitLabUsernameRule {
+    fn new() -> Self {
+        GitLabUsernameRule {
+            name: String::from("RULE_gitlab-username-setup"),
+            description: String::from(
+                "Ensure the GitLab account you are authenticated as owns the origin repository",
+            ),
+            level: RuleLevel::Warning,
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_description(&self) -> &str {
+        &self.description
+    }
+
+    fn get_level(&self) -> RuleLevel {
+        self.level.clone()
+    }
+
+    fn check(&self) -> Result<RuleOutput, Box<BGitError>> {
+        check_host_username(self.get_name(), GitHosting::GitLab)
+    }
+
+    fn try_fix(&self) -> Result<bool, Box<BGitError>> {
+        fix_host_username(self.get_name(), GitHosting::GitLab)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_creation() {
+        let rule = GitLabUsernameRule::new();
+        assert_eq!(rule.get_name(), "RULE_gitlab-username-setup");
+        assert_eq!(rule.get_level(), RuleLevel::Warning);
+    }
+}
@@ -1 +1,54 @@
+use crate::bgit_error::BGitError;
+use crate::remote_url::GitHosting;
+use crate::rules::a05_github_credentials_http::{check_http_credentials, fix_http_credentials};
+use crate::rules::{Rule, RuleLevel, RuleOutput};
 
+pub(crate) struct GitLabCredentialsHttpRule {
+    name: String,
+    description: String,
+    level: RuleLevel,
+}
+
+impl Rule for GitLabCredentialsHttpRule {
+    fn new() -> Self {
+        GitLabCredentialsHttpRule {
+            name: String::from("RULE_gitlab-credentials-http"),
+            description: String::from(
+                "Ensure GitLab HTTPS remotes use a credential helper instead of tokens in the URL",
+            ),
+            level: RuleLevel::Error,
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_description(&self) -> &str {
+        &self.description
+    }
+
+    fn get_level(&self) -> RuleLevel {
+        self.level.clone()
+    }
+
+    fn check(&self) -> Result<RuleOutput, Box<BGitError>> {
+        check_http_credentials(self.get_name(), GitHosting::GitLab)
+    }
+
+    fn try_fix(&self) -> Result<bool, Box<BGitError>> {
+        fix_http_credentials(self.get_name(), GitHosting::GitLab)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_creation() {
+        let rule = GitLabCredentialsHttpRule::new();
+        assert_eq!(rule.get_name(), "RULE_gitlab-credentials-http");
+        assert_eq!(rule.get_level(), RuleLevel::Error);
+    }
+}
@@ -1 +1,375 @@
+use crate::bgit_error::{BGitError, BGitErrorWorkflowType, NO_EVENT, NO_STEP};
+use crate::remote_url::{GitHosting, RemoteProtocol, RemoteUrl};
+use crate::rules::{Rule, RuleLevel, RuleOutput};
+use crate::ssh_config::{ssh_dir, SshConfig};
+use dialoguer::Confirm;
+use git2::Repository;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
+/// Identity files ssh tries when nothing else is configured
+const DEFAULT_IDENTITY_FILES: [&str; 5] = [
+    "id_ed25519",
+    "id_ecdsa",
+    "id_ed25519_sk",
+    "id_ecdsa_sk",
+    "id_rsa",
+];
+
+pub(crate) struct GitHubCredentialsSshRule {
+    name: String,
+    description: String,
+    level: RuleLevel,
+}
+
+impl Rule for GitHubCredentialsSshRule {
+    fn new() -> Self {
+        GitHubCredentialsSshRule {
+            name: String::from("RULE_github-credentials-ssh"),
+            description: String::from("Ensure an SSH key is available for GitHub SSH remotes"),
+            level: RuleLevel::Error,
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_description(&self) -> &str {
+        &self.description
+    }
+
+    fn get_level(&self) -> RuleLevel {
+        self.level.clone()
+    }
+
+    fn check(&self) -> Result<RuleOutput, Box<BGitError>> {
+        check_ssh_credentials(self.get_name(), GitHosting::GitHub)
+    }
+
+    fn try_fix(&self) -> Result<bool, Box<BGitError>> {
+        fix_ssh_credentials(self.get_name(), GitHosting::GitHub)
+    }
+}
+
+/// Shared by the GitHub and GitLab SSH credential rules
+pub(super) fn check_ssh_credentials(
+    rule_name: &str,
+    hosting: GitHosting,
+) -> Result<RuleOutput, Box<BGitError>> {
+    let repo = open_repo(rule_name)?;
+    let ssh_config = SshConfig::load();
+    let hosts = ssh_hosts(&repo, hosting, ssh_config.as_ref());
+    if hosts.is_empty() {
+        return Ok(RuleOutput::Success);
+    }
+
+    let agent_keys = agent_key_count().unwrap_or(0);
+    let mut missing = Vec::new();
+    for host in &hosts {
+        if agent_keys == 0 && identity_files(ssh_config.as_ref(), host).is_empty() {
+            missing.push(host.as_str());
+        }
+    }
+
+    if missing.is_empty() {
+        return Ok(RuleOutput::Success);
+    }
+    let agent_state = match env::var_os("SSH_AUTH_SOCK") {
+        Some(_) => "ssh-agent has no keys loaded",
+        None => "no ssh-agent is running (SSH_AUTH_SOCK is not set)",
+    };
+    Ok(RuleOutput::Exception(format!(
+        "No SSH key found for {}: there is no key in ~/.ssh or ~/.ssh/config for it and {}.\n\
+        Run:\n\
+          ssh-keygen -t ed25519 -C \"<your-email>\"\n\
+          ssh-add ~/.ssh/id_ed25519\n\
+        and add ~/.ssh/id_ed25519.pub to your {} account.",
+        missing.join(", "),
+        agent_state,
+        hosting.display_name()
+    )))
+}
+
+pub(super) fn fix_ssh_credentials(
+    rule_name: &str,
+    hosting: GitHosting,
+) -> Result<bool, Box<BGitError>> {
+    let repo = open_repo(rule_name)?;
+    let ssh_config = SshConfig::load();
+    let host = match ssh_hosts(&repo, hosting, ssh_config.as_ref())
+        .into_iter()
+        .next()
+    {
+        Some(host) => host,
+        None => return Ok(true),
+    };
+    let ssh_dir = ssh_dir()
+        .ok_or_else(|| rule_error(rule_name, "Failed to find ~/.ssh", "HOME is not set"))?;
+
+    if Command::new("ssh-keygen").arg("-?").output().is_err() {
+        eprintln!("ssh-keygen was not found. Install OpenSSH and run bgit again.");
+        return Ok(false);
+    }
+
+    let key_path = new_key_path(&ssh_dir, &host);
+    let confirmed = Confirm::new()
+        .with_prompt(format!(
+            "Generate a new ed25519 SSH key at {}?",
+            key_path.display()
+        ))
+        .default(true)
+        .interact()
+        .map_err(|e| rule_error(rule_name, "Input Error", e))?;
+    if !confirmed {
+        return Ok(false);
+    }
+
+    fs::create_dir_all(&ssh_dir)
+        .map_err(|e| rule_error(rule_name, "Failed to create ~/.ssh", e))?;
+    let comment = repo
+        .config()
+        .and_then(|config| config.get_string("user.email"))
+        .unwrap_or_else(|_| format!("bgit@{}", host));
+    // ssh-keygen asks for the passphrase itself
+    let generated = Command::new("ssh-keygen")
+        .args(["-t", "ed25519", "-C", &comment, "-f"])
+        .arg(&key_path)
+        .status()
+        .map_err(|e| rule_error(rule_name, "Failed to run ssh-keygen", e))?;
+    if !generated.success() {
+        return Ok(false);
+    }
+
+    // ssh only picks up non-default key names through ~/.ssh/config
+    if !DEFAULT_IDENTITY_FILES
+        .iter()
+        .any(|name| key_path.ends_with(name))
+    {
+        add_host_identity(&ssh_dir.join("config"), &host, &key_path)
+            .map_err(|e| rule_error(rule_name, "Failed to update ~/.ssh/config", e))?;
+    }
+
+    if env::var_os("SSH_AUTH_SOCK").is_some() {
+        let added = Command::new("ssh-add")
+            .arg(&key_path)
+            .status()
+            .map_err(|e| rule_error(rule_name, "Failed to run ssh-add", e))?;
+        if !added.success() {
+            eprintln!(
+                "Failed to add the key to ssh-agent, ssh will ask for the passphrase instead."
+            );
+        }
+    } else {
+        eprintln!(
+            "No ssh-agent is running. Start one with `eval \"$(ssh-agent -s)\"` and run `ssh-add {}`.",
+            key_path.display()
+        );
+    }
+
+    let public_key = fs::read_to_string(key_path.with_extension("pub"))
+        .map_err(|e| rule_error(rule_name, "Failed to read public key", e))?;
+    println!(
+        "Add this public key to your {} account at {}:\n\n{}",
+        hosting.display_name(),
+        ssh_keys_settings_url(hosting, &host),
+        public_key.trim()
+    );
+
+    Ok(true)
+}
+
+fn open_repo(rule_name: &str) -> Result<Repository, Box<BGitError>> {
+    let cwd = env::current_dir().map_err(|e| rule_error(rule_name, "Failed to get cwd", e))?;
+    Repository::discover(cwd).map_err(|e| rule_error(rule_name, "Failed to open repository", e))
+}
+
+/// Hosts of SSH remotes on `hosting`, as written in the URL. Aliases from
+/// `~/.ssh/config` are resolved to decide whether a remote is on `hosting`.
+fn ssh_hosts(
+    repo: &Repository,
+    hosting: GitHosting,
+    ssh_config: Option<&SshConfig>,
+) -> Vec<String> {
+    let mut hosts = Vec::new();
+    let remote_names = match repo.remotes() {
+        Ok(remote_names) => remote_names,
+        Err(_) => return hosts,
+    };
+    for remote_name in remote_names.iter().flatten() {
+        let remote = match repo.find_remote(remote_name) {
+            Ok(remote) => remote,
+            Err(_) => continue,
+        };
+        for url in [remote.url(), remote.pushurl()].into_iter().flatten() {
+            let remote_url = match RemoteUrl::parse(url) {
+                Some(remote_url) if remote_url.protocol == RemoteProtocol::Ssh => remote_url,
+                _ => continue,
+            };
+            let hostname = ssh_config
+                .map(|ssh_config| ssh_config.resolve_host(&remote_url.host))
+                .unwrap_or_else(|| remote_url.host.clone());
+            if hosting.matches_host(&hostname) && !hosts.contains(&remote_url.host) {
+                hosts.push(remote_url.host);
+            }
+        }
+    }
+    hosts
+}
+
+/// Private keys ssh would offer for `host`: `IdentityFile` entries of `~/.ssh/config`
+/// and the default key names in `~/.ssh`, only when they exist
+pub(crate) fn identity_files(ssh_config: Option<&SshConfig>, host: &str) -> Vec<PathBuf> {
+    let ssh_dir = match ssh_dir() {
+        Some(ssh_dir) => ssh_dir,
+        None => return Vec::new(),
+    };
+    let configured = ssh_config
+        .map(|ssh_config| ssh_config.options_for(host, "identityfile"))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|path| expand_identity_path(path, &ssh_dir, host));
+    let defaults = DEFAULT_IDENTITY_FILES.iter().map(|name| ssh_dir.join(name));
+    configured
+        .chain(defaults)
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// `~` and the `%d` / `%h` tokens supported in `IdentityFile`
+fn expand_identity_path(path: &str, ssh_dir: &Path, host: &str) -> PathBuf {
+    let home = ssh_dir.parent().unwrap_or(ssh_dir).to_string_lossy();
+    let path = path.replace("%d", &home).replace("%h", host);
+    match path.strip_prefix("~/") {
+        Some(rest) => ssh_dir.parent().unwrap_or(ssh_dir).join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+/// Number of keys in ssh-agent, `None` when no agent is reachable
+fn agent_key_count() -> Option<usize> {
+    env::var_os("SSH_AUTH_SOCK")?;
+    let output = Command::new("ssh-add").arg("-l").output().ok()?;
+    // 1 means the agent has no identities, 2 that it cannot be reached
+    match output.status.code() {
+        Some(0) => Some(String::from_utf8_lossy(&output.stdout).lines().count()),
+        Some(1) => Some(0),
+        _ => None,
+    }
+}
+
+/// `~/.ssh/id_ed25519`, or a host specific name when that one is already taken
+fn new_key_path(ssh_dir: &Path, host: &str) -> PathBuf {
+    let default_path = ssh_dir.join("id_ed25519");
+    if !default_path.exists() {
+        return default_path;
+    }
+    ssh_dir.join(format!("id_ed25519_{}", host.replace('.', "_")))
+}
+
+fn add_host_identity(config_path: &Path, host: &str, key_path: &Path) -> std::io::Result<()> {
+    let existing = fs::read_to_string(config_path).unwrap_or_default();
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(config_path)?;
+    if !existing.is_empty() && !existing.ends_with('\n') {
+        writeln!(file)?;
+    }
+    write!(
+        file,
+        "\nHost {}\n    IdentityFile {}\n    AddKeysToAgent yes\n",
+        host,
+        key_path.display()
+    )
+}
+
+fn ssh_keys_settings_url(hosting: GitHosting, host: &str) -> String {
+    match hosting {
+        GitHosting::GitHub => String::from("https://github.com/settings/ssh/new"),
+        GitHosting::GitLab => format!("https://{}/-/user_settings/ssh_keys", host),
+    }
+}
+
+fn rule_error(rule_name: &str, name: &str, error: impl std::fmt::Display) -> Box<BGitError> {
+    Box::new(BGitError::new(
+        name,
+        &error.to_string(),
+        BGitErrorWorkflowType::Rules,
+        NO_STEP,
+        NO_EVENT,
+        rule_name,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_rule_creation() {
+        let rule = GitHubCredentialsSshRule::new();
+        assert_eq!(rule.get_name(), "RULE_github-credentials-ssh");
+        assert_eq!(rule.get_level(), RuleLevel::Error);
+    }
+
+    #[test]
+    fn test_ssh_hosts() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        repo.remote("origin", "git@github.com:owner/repo.git")
+            .unwrap();
+        repo.remote("https", "https://github.com/owner/other.git")
+            .unwrap();
+        repo.remote("work", "work:group/repo.git").unwrap();
+
+        assert_eq!(
+            ssh_hosts(&repo, GitHosting::GitHub, None),
+            vec![String::from("github.com")]
+        );
+        assert!(ssh_hosts(&repo, GitHosting::GitLab, None).is_empty());
+
+        let ssh_config = SshConfig::parse("Host work\n    HostName gitlab.example.com\n");
+        assert_eq!(
+            ssh_hosts(&repo, GitHosting::GitLab, Some(&ssh_config)),
+            vec![String::from("work")]
+        );
+    }
+
+    #[test]
+    fn test_key_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let ssh_dir = temp_dir.path().join(".ssh");
+        fs::create_dir_all(&ssh_dir).unwrap();
+
+        assert_eq!(
+            expand_identity_path("~/.ssh/work_key", &ssh_dir, "github.com"),
+            ssh_dir.join("work_key")
+        );
+        assert_eq!(
+            expand_identity_path("%d/.ssh/key_%h", &ssh_dir, "github.com"),
+            ssh_dir.join("key_github.com")
+        );
+
+        assert_eq!(
+            new_key_path(&ssh_dir, "github.com"),
+            ssh_dir.join("id_ed25519")
+        );
+        fs::write(ssh_dir.join("id_ed25519"), "").unwrap();
+        let key_path = new_key_path(&ssh_dir, "gitlab.example.com");
+        assert_eq!(key_path, ssh_dir.join("id_ed25519_gitlab_example_com"));
+
+        let config_path = ssh_dir.join("config");
+        fs::write(&config_path, "Host other\n    User me").unwrap();
+        add_host_identity(&config_path, "gitlab.example.com", &key_path).unwrap();
+        let ssh_config = SshConfig::parse(&fs::read_to_string(&config_path).unwrap());
+        assert_eq!(
+            ssh_config.options_for("gitlab.example.com", "identityfile"),
+            vec![key_path.to_str().unwrap()]
+        );
+    }
+}
@@ -1 +1,52 @@
+use crate::bgit_error::BGitError;
+use crate::remote_url::GitHosting;
+use crate::rules::a07_github_credentials_ssh::{check_ssh_credentials, fix_ssh_credentials};
+use crate::rules::{Rule, RuleLevel, RuleOutput};
 
+pub(crate) struct GitLabCredentialsSshRule {
+    name: String,
+    description: String,
+    level: RuleLevel,
+}
+
+impl Rule for GitLabCredentialsSshRule {
+    fn new() -> Self {
+        GitLabCredentialsSshRule {
+            name: String::from("RULE_gitlab-credentials-ssh"),
+            description: String::from("Ensure an SSH key is available for GitLab SSH remotes"),
+            level: RuleLevel::Error,
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_description(&self) -> &str {
+        &self.description
+    }
+
+    fn get_level(&self) -> RuleLevel {
+        self.level.clone()
+    }
+
+    fn check(&self) -> Result<RuleOutput, Box<BGitError>> {
+        check_ssh_credentials(self.get_name(), GitHosting::GitLab)
+    }
+
+    fn try_fix(&self) -> Result<bool, Box<BGitError>> {
+        fix_ssh_credentials(self.get_name(), GitHosting::GitLab)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_creation() {
+        let rule = GitLabCredentialsSshRule::new();
+        assert_eq!(rule.get_name(), "RULE_gitlab-credentials-ssh");
+        assert_eq!(rule.get_level(), RuleLevel::Error);
+    }
+}
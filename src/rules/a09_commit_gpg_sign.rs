@@ -1 +1,262 @@
+use crate::bgit_config::BGitConfig;
+use crate::bgit_error::{BGitError, BGitErrorWorkflowType, NO_EVENT, NO_STEP};
+use crate::rules::{Rule, RuleLevel, RuleOutput};
+use crate::signing::CommitSigner;
+use dialoguer::{theme::ColorfulTheme, Select};
+use git2::{Config, ConfigLevel, Repository};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
 
+/// A key that can be offered to the user in `try_fix`
+struct SigningKeyCandidate {
+    label: String,
+    format: &'static str,
+    signing_key: String,
+}
+
+pub(crate) struct CommitGpgSignRule {
+    name: String,
+    description: String,
+    level: RuleLevel,
+}
+
+impl Rule for CommitGpgSignRule {
+    fn new() -> Self {
+        CommitGpgSignRule {
+            name: String::from("RULE_commit-gpg-sign"),
+            description: String::from(
+                "Ensure commits are signed with a configured and usable GPG or SSH key",
+            ),
+            level: RuleLevel::Error,
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_description(&self) -> &str {
+        &self.description
+    }
+
+    /// Repositories that don't sign are left alone, the rule only enforces signing
+    /// when `commit.gpgSign` is on or `.bgit/config` sets `signing.required`
+    fn get_level(&self) -> RuleLevel {
+        let repo = Self::discover_repo();
+        let required = match self.open_config() {
+            Ok(config) => Self::signing_required(repo.as_ref(), &config),
+            Err(_) => false,
+        };
+        if required {
+            self.level.clone()
+        } else {
+            RuleLevel::Skip
+        }
+    }
+
+    fn check(&self) -> Result<RuleOutput, Box<BGitError>> {
+        let config = self.open_config()?;
+        match CommitSigner::from_config(&config) {
+            Err(e) => Ok(RuleOutput::Exception(format!(
+                "Commit signing is misconfigured: {}",
+                e
+            ))),
+            Ok(None) => Ok(RuleOutput::Exception(String::from(
+                "Commit signing is not enabled.\n\
+                Run:\n\
+                  git config user.signingKey <key id or path to ssh key>\n\
+                  git config gpg.format <openpgp|ssh>\n\
+                  git config commit.gpgSign true",
+            ))),
+            Ok(Some(signer)) => match signer.check_usable() {
+                Ok(()) => Ok(RuleOutput::Success),
+                Err(e) => Ok(RuleOutput::Exception(format!(
+                    "Commit signing is enabled but cannot be used: {}",
+                    e
+                ))),
+            },
+        }
+    }
+
+    fn try_fix(&self) -> Result<bool, Box<BGitError>> {
+        let config = self.open_config()?;
+        let mut writable_config = config
+            .open_level(ConfigLevel::Local)
+            .or_else(|_| config.open_level(ConfigLevel::Global))
+            .map_err(|e| self.rule_error("Failed to open git config", e))?;
+
+        // Signing key already configured, only signing itself is switched off
+        if config.get_string("user.signingKey").is_ok()
+            && matches!(CommitSigner::from_config(&config), Ok(None))
+        {
+            writable_config
+                .set_bool("commit.gpgSign", true)
+                .map_err(|e| self.rule_error("Failed to write git config", e))?;
+            return Ok(true);
+        }
+
+        let candidates = Self::find_signing_keys();
+        if candidates.is_empty() {
+            eprintln!(
+                "No GPG or SSH keys found. Create one with `ssh-keygen -t ed25519` \
+                or `gpg --full-generate-key` and run bgit again."
+            );
+            return Ok(false);
+        }
+
+        let mut options: Vec<&str> = candidates
+            .iter()
+            .map(|candidate| candidate.label.as_str())
+            .collect();
+        options.push("Skip");
+        let selection = Select::with_theme(&ColorfulTheme::default())
+            .with_prompt("Which key should be used to sign commits?")
+            .default(0)
+            .items(&options)
+            .interact()
+            .map_err(|e| self.rule_error("Input Error", e))?;
+
+        let candidate = match candidates.get(selection) {
+            Some(candidate) => candidate,
+            None => return Ok(false),
+        };
+
+        writable_config
+            .set_str("gpg.format", candidate.format)
+            .and_then(|_| writable_config.set_str("user.signingKey", &candidate.signing_key))
+            .and_then(|_| writable_config.set_bool("commit.gpgSign", true))
+            .map_err(|e| self.rule_error("Failed to write git config", e))?;
+
+        Ok(true)
+    }
+}
+
+impl CommitGpgSignRule {
+    fn discover_repo() -> Option<Repository> {
+        env::current_dir()
+            .ok()
+            .and_then(|cwd| Repository::discover(cwd).ok())
+    }
+
+    fn open_config(&self) -> Result<Config, Box<BGitError>> {
+        match Self::discover_repo() {
+            Some(repo) => repo.config(),
+            None => Config::open_default(),
+        }
+        .map_err(|e| self.rule_error("Failed to open git config", e))
+    }
+
+    fn signing_required(repo: Option<&Repository>, config: &Config) -> bool {
+        config.get_bool("commit.gpgSign").unwrap_or(false)
+            || repo
+                .and_then(|repo| BGitConfig::load(repo).get_bool("signing.required"))
+                .unwrap_or(false)
+    }
+
+    /// Private SSH keys with a matching `.pub` in `~/.ssh` and secret GPG keys
+    fn find_signing_keys() -> Vec<SigningKeyCandidate> {
+        let mut candidates = Vec::new();
+
+        if let Some(ssh_dir) = env::var_os("HOME").map(|home| PathBuf::from(home).join(".ssh")) {
+            if let Ok(entries) = fs::read_dir(&ssh_dir) {
+                let mut public_keys: Vec<PathBuf> = entries
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .filter(|path| path.extension().is_some_and(|extension| extension == "pub"))
+                    .collect();
+                public_keys.sort();
+                for public_key in public_keys {
+                    let private_key = public_key.with_extension("");
+                    if private_key.is_file() {
+                        candidates.push(SigningKeyCandidate {
+                            label: format!("SSH key {}", private_key.display()),
+                            format: "ssh",
+                            signing_key: private_key.to_string_lossy().to_string(),
+                        });
+                    }
+                }
+            }
+        }
+
+        if let Ok(output) = Command::new("gpg")
+            .args(["--list-secret-keys", "--with-colons"])
+            .output()
+        {
+            let listing = String::from_utf8_lossy(&output.stdout);
+            let mut key_id = None;
+            for line in listing.lines() {
+                let fields: Vec<&str> = line.split(':').collect();
+                match fields.first() {
+                    Some(&"sec") => key_id = fields.get(4).map(|id| id.to_string()),
+                    Some(&"uid") => {
+                        if let (Some(id), Some(user)) = (key_id.take(), fields.get(9)) {
+                            candidates.push(SigningKeyCandidate {
+                                label: format!("GPG key {} ({})", id, user),
+                                format: "openpgp",
+                                signing_key: id,
+                            });
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        candidates
+    }
+
+    fn rule_error(&self, name: &str, error: impl std::fmt::Display) -> Box<BGitError> {
+        Box::new(BGitError::new(
+            name,
+            &error.to_string(),
+            BGitErrorWorkflowType::Rules,
+            NO_STEP,
+            NO_EVENT,
+            self.get_name(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_rule_creation() {
+        let rule = CommitGpgSignRule::new();
+        assert_eq!(rule.get_name(), "RULE_commit-gpg-sign");
+        assert_eq!(rule.level, RuleLevel::Error);
+    }
+
+    #[test]
+    fn test_signing_required() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        // Set locally so a global commit.gpgSign of the machine doesn't leak in
+        repo.config()
+            .unwrap()
+            .set_bool("commit.gpgSign", false)
+            .unwrap();
+        let config = repo.config().unwrap();
+        assert!(!CommitGpgSignRule::signing_required(Some(&repo), &config));
+
+        fs::create_dir_all(temp_dir.path().join(".bgit")).unwrap();
+        fs::write(
+            temp_dir.path().join(".bgit/config"),
+            "[signing]\n\trequired = true\n",
+        )
+        .unwrap();
+        assert!(CommitGpgSignRule::signing_required(Some(&repo), &config));
+
+        fs::remove_file(temp_dir.path().join(".bgit/config")).unwrap();
+        repo.config()
+            .unwrap()
+            .set_bool("commit.gpgSign", true)
+            .unwrap();
+        let config = repo.config().unwrap();
+        assert!(CommitGpgSignRule::signing_required(Some(&repo), &config));
+    }
+}
@@ -0,0 +1,285 @@
+use crate::bgit_config::BGitConfig;
+use crate::bgit_error::{BGitError, BGitErrorWorkflowType, NO_EVENT, NO_STEP};
+use crate::rules::{Rule, RuleLevel, RuleOutput};
+use dialoguer::{theme::ColorfulTheme, Select};
+use git2::{Config, ConfigLevel, Repository};
+use std::env;
+
+/// Defaults from the rule docs. Teams override or extend them in `.bgit/config`:
+/// ```text
+/// [default-config "pull"]
+///     rebase = true
+/// ```
+const RECOMMENDED_DEFAULTS: [(&str, &str); 5] = [
+    ("init.defaultBranch", "main"),
+    ("pull.rebase", "false"),
+    (
+        "core.autocrlf",
+        if cfg!(windows) { "true" } else { "input" },
+    ),
+    ("core.safecrlf", "true"),
+    ("color.ui", "auto"),
+];
+
+#[derive(Debug, PartialEq)]
+struct ConfigMismatch {
+    key: String,
+    current: Option<String>,
+    recommended: String,
+}
+
+pub(crate) struct GitDefaultConfigRule {
+    name: String,
+    description: String,
+    level: RuleLevel,
+}
+
+impl Rule for GitDefaultConfigRule {
+    fn new() -> Self {
+        GitDefaultConfigRule {
+            name: String::from("RULE_git-default-config"),
+            description: String::from(
+                "Ensure essential Git default configurations are set to the recommended values",
+            ),
+            level: RuleLevel::Warning,
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_description(&self) -> &str {
+        &self.description
+    }
+
+    fn get_level(&self) -> RuleLevel {
+        self.level.clone()
+    }
+
+    fn check(&self) -> Result<RuleOutput, Box<BGitError>> {
+        let repo = Self::discover_repo();
+        let config = self.open_config(repo.as_ref())?;
+        let mismatches = Self::find_mismatches(&config, &Self::recommended_values(repo.as_ref()));
+
+        if mismatches.is_empty() {
+            return Ok(RuleOutput::Success);
+        }
+
+        let report = mismatches
+            .iter()
+            .map(|mismatch| match &mismatch.current {
+                Some(current) => format!(
+                    "  {}: '{}' (recommended: {})",
+                    mismatch.key, current, mismatch.recommended
+                ),
+                None => format!(
+                    "  {}: not set (recommended: {})",
+                    mismatch.key, mismatch.recommended
+                ),
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        let commands = mismatches
+            .iter()
+            .map(|mismatch| {
+                format!(
+                    "git config --global {} {}",
+                    mismatch.key, mismatch.recommended
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        Ok(RuleOutput::Exception(format!(
+            "Git default configuration differs from the recommended values:\n{}\n\
+            Run:\n{}",
+            report, commands
+        )))
+    }
+
+    fn try_fix(&self) -> Result<bool, Box<BGitError>> {
+        let repo = Self::discover_repo();
+        let config = self.open_config(repo.as_ref())?;
+        let mismatches = Self::find_mismatches(&config, &Self::recommended_values(repo.as_ref()));
+
+        let mut options = vec!["Set globally (all repositories)", "Skip"];
+        if repo.is_some() {
+            options.insert(0, "Set in this repository only");
+        }
+
+        let mut all_fixed = true;
+        for mismatch in mismatches {
+            let selection = Select::with_theme(&ColorfulTheme::default())
+                .with_prompt(format!(
+                    "Set {} to '{}'?",
+                    mismatch.key, mismatch.recommended
+                ))
+                .default(0)
+                .items(&options)
+                .interact()
+                .map_err(|e| self.rule_error("Input Error", e))?;
+
+            let mut scoped_config = match options[selection] {
+                "Set in this repository only" => config.open_level(ConfigLevel::Local),
+                "Set globally (all repositories)" => {
+                    Config::open_default().and_then(|mut default| default.open_global())
+                }
+                _ => {
+                    all_fixed = false;
+                    continue;
+                }
+            }
+            .map_err(|e| self.rule_error("Failed to open git config", e))?;
+
+            scoped_config
+                .set_str(&mismatch.key, &mismatch.recommended)
+                .map_err(|e| self.rule_error("Failed to write git config", e))?;
+        }
+
+        Ok(all_fixed)
+    }
+}
+
+impl GitDefaultConfigRule {
+    fn discover_repo() -> Option<Repository> {
+        env::current_dir()
+            .ok()
+            .and_then(|cwd| Repository::discover(cwd).ok())
+    }
+
+    /// Repository config layered over global and system config, or only the latter outside a repo
+    fn open_config(&self, repo: Option<&Repository>) -> Result<Config, Box<BGitError>> {
+        match repo {
+            Some(repo) => repo.config(),
+            None => Config::open_default(),
+        }
+        .map_err(|e| self.rule_error("Failed to open git config", e))
+    }
+
+    fn recommended_values(repo: Option<&Repository>) -> Vec<(String, String)> {
+        let mut recommended: Vec<(String, String)> = RECOMMENDED_DEFAULTS
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+
+        let team_values = match repo {
+            Some(repo) => BGitConfig::load(repo).get_section("default-config"),
+            None => Vec::new(),
+        };
+        for (key, value) in team_values {
+            match recommended
+                .iter_mut()
+                .find(|(default_key, _)| default_key.eq_ignore_ascii_case(&key))
+            {
+                Some(entry) => entry.1 = value,
+                None => recommended.push((key, value)),
+            }
+        }
+
+        recommended
+    }
+
+    fn find_mismatches(config: &Config, recommended: &[(String, String)]) -> Vec<ConfigMismatch> {
+        recommended
+            .iter()
+            .filter_map(|(key, recommended_value)| {
+                let current = config.get_string(key).ok();
+                let matches = match &current {
+                    Some(current) => Self::values_match(current, recommended_value),
+                    None => false,
+                };
+                (!matches).then(|| ConfigMismatch {
+                    key: key.clone(),
+                    current,
+                    recommended: recommended_value.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Booleans are compared the way git reads them, so `yes` matches `true`
+    fn values_match(current: &str, recommended: &str) -> bool {
+        match (Config::parse_bool(current), Config::parse_bool(recommended)) {
+            (Ok(current), Ok(recommended)) => current == recommended,
+            _ => current == recommended,
+        }
+    }
+
+    fn rule_error(&self, name: &str, error: impl std::fmt::Display) -> Box<BGitError> {
+        Box::new(BGitError::new(
+            name,
+            &error.to_string(),
+            BGitErrorWorkflowType::Rules,
+            NO_STEP,
+            NO_EVENT,
+            self.get_name(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_rule_creation() {
+        let rule = GitDefaultConfigRule::new();
+        assert_eq!(rule.get_name(), "RULE_git-default-config");
+        assert_eq!(rule.get_level(), RuleLevel::Warning);
+    }
+
+    #[test]
+    fn test_team_values_override_defaults() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        fs::create_dir(temp_dir.path().join(".bgit")).unwrap();
+        fs::write(
+            temp_dir.path().join(".bgit").join("config"),
+            "[default-config \"pull\"]\n\trebase = true\n[default-config \"fetch\"]\n\tprune = true\n",
+        )
+        .unwrap();
+
+        let recommended = GitDefaultConfigRule::recommended_values(Some(&repo));
+        assert!(recommended.contains(&(String::from("pull.rebase"), String::from("true"))));
+        assert!(recommended.contains(&(String::from("fetch.prune"), String::from("true"))));
+        assert_eq!(recommended.len(), RECOMMENDED_DEFAULTS.len() + 1);
+    }
+
+    #[test]
+    fn test_find_mismatches() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("gitconfig");
+        fs::write(
+            &config_path,
+            "[init]\n\tdefaultBranch = master\n[core]\n\tsafecrlf = yes\n",
+        )
+        .unwrap();
+        let config = Config::open(&config_path).unwrap();
+
+        let recommended = vec![
+            (String::from("init.defaultBranch"), String::from("main")),
+            (String::from("core.safecrlf"), String::from("true")),
+            (String::from("color.ui"), String::from("auto")),
+        ];
+        let mismatches = GitDefaultConfigRule::find_mismatches(&config, &recommended);
+        assert_eq!(
+            mismatches,
+            vec![
+                ConfigMismatch {
+                    key: String::from("init.defaultBranch"),
+                    current: Some(String::from("master")),
+                    recommended: String::from("main"),
+                },
+                ConfigMismatch {
+                    key: String::from("color.ui"),
+                    current: None,
+                    recommended: String::from("auto"),
+                },
+            ]
+        );
+    }
+}
@@ -1 +1,234 @@
+use crate::bgit_config::BGitConfig;
+use crate::bgit_error::{BGitError, BGitErrorWorkflowType, NO_EVENT, NO_STEP};
+use crate::remote_url::{RemoteProtocol, RemoteUrl};
+use crate::rules::{Rule, RuleLevel, RuleOutput};
+use dialoguer::Confirm;
+use git2::Repository;
+use std::env;
 
+/// Which URL form the team wants, read from `remote-protocol.preferred` in `.bgit/config`.
+/// `ssh` (the default) converts to `git@host:owner/repo.git`, `ssh-url` to
+/// `ssh://git@host/owner/repo.git` and `https` to `https://host/owner/repo.git`
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PreferredProtocol {
+    Ssh,
+    SshUrl,
+    Https,
+}
+
+#[derive(Debug, PartialEq)]
+struct RemoteMismatch {
+    remote: String,
+    push_url: bool,
+    current: String,
+    suggested: String,
+}
+
+pub(crate) struct GitRemoteHttpSshRule {
+    name: String,
+    description: String,
+    level: RuleLevel,
+}
+
+impl Rule for GitRemoteHttpSshRule {
+    fn new() -> Self {
+        GitRemoteHttpSshRule {
+            name: String::from("RULE_git-remote-http-ssh"),
+            description: String::from(
+                "Ensure remotes use the protocol (SSH or HTTPS) preferred by the team",
+            ),
+            level: RuleLevel::Warning,
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_description(&self) -> &str {
+        &self.description
+    }
+
+    fn get_level(&self) -> RuleLevel {
+        self.level.clone()
+    }
+
+    fn check(&self) -> Result<RuleOutput, Box<BGitError>> {
+        let repo = self.open_repo()?;
+        let preferred = Self::preferred_protocol(&repo);
+        let mismatches = self
+            .find_mismatches(&repo, preferred)
+            .map_err(|e| self.rule_error("Failed to inspect remotes", e))?;
+
+        if mismatches.is_empty() {
+            return Ok(RuleOutput::Success);
+        }
+
+        let report = mismatches
+            .iter()
+            .map(|mismatch| {
+                format!(
+                    "  {}{}: {} -> {}",
+                    mismatch.remote,
+                    if mismatch.push_url { " (push)" } else { "" },
+                    mismatch.current,
+                    mismatch.suggested
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        Ok(RuleOutput::Exception(format!(
+            "The following remotes don't use the preferred protocol:\n{}\n\
+            Run:\n\
+              git remote set-url <remote> <url>",
+            report
+        )))
+    }
+
+    fn try_fix(&self) -> Result<bool, Box<BGitError>> {
+        let repo = self.open_repo()?;
+        let mismatches = self
+            .find_mismatches(&repo, Self::preferred_protocol(&repo))
+            .map_err(|e| self.rule_error("Failed to inspect remotes", e))?;
+
+        let mut all_fixed = true;
+        for mismatch in mismatches {
+            let confirmed = Confirm::new()
+                .with_prompt(format!(
+                    "Change remote '{}' from {} to {}?",
+                    mismatch.remote, mismatch.current, mismatch.suggested
+                ))
+                .default(true)
+                .interact()
+                .map_err(|e| self.rule_error("Input Error", e))?;
+            if !confirmed {
+                all_fixed = false;
+                continue;
+            }
+
+            let result = match mismatch.push_url {
+                true => repo.remote_set_pushurl(&mismatch.remote, Some(&mismatch.suggested)),
+                false => repo.remote_set_url(&mismatch.remote, &mismatch.suggested),
+            };
+            result.map_err(|e| self.rule_error("Failed to update remote URL", e))?;
+        }
+
+        Ok(all_fixed)
+    }
+}
+
+impl GitRemoteHttpSshRule {
+    fn open_repo(&self) -> Result<Repository, Box<BGitError>> {
+        let cwd = env::current_dir().map_err(|e| self.rule_error("Failed to get cwd", e))?;
+        Repository::discover(cwd).map_err(|e| self.rule_error("Failed to open repository", e))
+    }
+
+    fn preferred_protocol(repo: &Repository) -> PreferredProtocol {
+        match BGitConfig::load(repo)
+            .get_string("remote-protocol.preferred")
+            .map(|preferred| preferred.to_lowercase())
+            .as_deref()
+        {
+            Some("https") => PreferredProtocol::Https,
+            Some("ssh-url") => PreferredProtocol::SshUrl,
+            _ => PreferredProtocol::Ssh,
+        }
+    }
+
+    fn find_mismatches(
+        &self,
+        repo: &Repository,
+        preferred: PreferredProtocol,
+    ) -> Result<Vec<RemoteMismatch>, git2::Error> {
+        let mut mismatches = Vec::new();
+        for remote_name in repo.remotes()?.iter().flatten() {
+            let remote = repo.find_remote(remote_name)?;
+            let urls = [(false, remote.url()), (true, remote.pushurl())];
+            for (push_url, url) in urls {
+                let current = match url {
+                    Some(url) => url,
+                    None => continue,
+                };
+                if let Some(suggested) = Self::suggest_url(current, preferred) {
+                    mismatches.push(RemoteMismatch {
+                        remote: remote_name.to_owned(),
+                        push_url,
+                        current: current.to_owned(),
+                        suggested,
+                    });
+                }
+            }
+        }
+        Ok(mismatches)
+    }
+
+    /// `None` when the URL already uses the preferred protocol or is not a network URL
+    fn suggest_url(url: &str, preferred: PreferredProtocol) -> Option<String> {
+        let remote_url = RemoteUrl::parse(url)?;
+        match (preferred, remote_url.protocol) {
+            (PreferredProtocol::Https, RemoteProtocol::Https) => None,
+            (PreferredProtocol::Https, _) => Some(remote_url.to_https()),
+            (PreferredProtocol::Ssh | PreferredProtocol::SshUrl, RemoteProtocol::Ssh) => None,
+            (PreferredProtocol::Ssh, _) => Some(remote_url.to_scp()),
+            (PreferredProtocol::SshUrl, _) => Some(remote_url.to_ssh_url()),
+        }
+    }
+
+    fn rule_error(&self, name: &str, error: impl std::fmt::Display) -> Box<BGitError> {
+        Box::new(BGitError::new(
+            name,
+            &error.to_string(),
+            BGitErrorWorkflowType::Rules,
+            NO_STEP,
+            NO_EVENT,
+            self.get_name(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_rule_creation() {
+        let rule = GitRemoteHttpSshRule::new();
+        assert_eq!(rule.get_name(), "RULE_git-remote-http-ssh");
+        assert_eq!(rule.get_level(), RuleLevel::Warning);
+    }
+
+    #[test]
+    fn test_find_mismatches() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        repo.remote("origin", "https://github.com/owner/repo.git")
+            .unwrap();
+        repo.remote("upstream", "git@github.com:upstream/repo.git")
+            .unwrap();
+        repo.remote("local", "/srv/git/repo.git").unwrap();
+
+        let rule = GitRemoteHttpSshRule::new();
+        let mismatches = rule.find_mismatches(&repo, PreferredProtocol::Ssh).unwrap();
+        assert_eq!(
+            mismatches,
+            vec![RemoteMismatch {
+                remote: String::from("origin"),
+                push_url: false,
+                current: String::from("https://github.com/owner/repo.git"),
+                suggested: String::from("git@github.com:owner/repo.git"),
+            }]
+        );
+
+        let mismatches = rule
+            .find_mismatches(&repo, PreferredProtocol::Https)
+            .unwrap();
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].remote, "upstream");
+        assert_eq!(
+            mismatches[0].suggested,
+            "https://github.com/upstream/repo.git"
+        );
+    }
+}
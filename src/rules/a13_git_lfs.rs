@@ -1 +1,356 @@
+use crate::bgit_config::BGitConfig;
+use crate::bgit_error::{BGitError, BGitErrorWorkflowType, NO_EVENT, NO_STEP};
+use crate::rules::{Rule, RuleLevel, RuleOutput};
+use crate::util::program_exists;
+use dialoguer::{theme::ColorfulTheme, Select};
+use git2::{AttrCheckFlags, Delta, Repository};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+use std::{env, io};
 
+/// Default threshold of 5 MB as specified in the docs.
+/// Can be overridden with `lfs.threshold` (e.g. `10m`) in `.bgit/config`
+const DEFAULT_LFS_THRESHOLD_BYTES: u64 = 5 * 1024 * 1024;
+
+const LFS_ATTRIBUTES: &str = "filter=lfs diff=lfs merge=lfs -text";
+
+#[derive(Debug, PartialEq)]
+struct LargeStagedFile {
+    path: String,
+    size: u64,
+    /// `.gitattributes` already routes the path through LFS, but the staged
+    /// blob is the raw content, i.e. it was staged without the LFS filter
+    lfs_tracked: bool,
+}
+
+pub(crate) struct GitLfsRule {
+    name: String,
+    description: String,
+    level: RuleLevel,
+}
+
+impl Rule for GitLfsRule {
+    fn new() -> Self {
+        GitLfsRule {
+            name: String::from("RULE_git-lfs"),
+            description: String::from(
+                "Ensure that large files are tracked with Git LFS instead of being committed directly",
+            ),
+            level: RuleLevel::Warning,
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_description(&self) -> &str {
+        &self.description
+    }
+
+    fn get_level(&self) -> RuleLevel {
+        self.level.clone()
+    }
+
+    fn check(&self) -> Result<RuleOutput, Box<BGitError>> {
+        let repo = self.open_repo()?;
+        let threshold = Self::threshold(&repo);
+        let large_files = self
+            .find_large_staged_files(&repo, threshold)
+            .map_err(|e| self.rule_error("Failed to inspect staged files", e))?;
+
+        if large_files.is_empty() {
+            return Ok(RuleOutput::Success);
+        }
+
+        let report = large_files
+            .iter()
+            .map(|file| {
+                let note = if file.lfs_tracked {
+                    " - tracked by LFS, but staged without the LFS filter"
+                } else {
+                    ""
+                };
+                format!("  {} ({}){}", file.path, Self::format_size(file.size), note)
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        Ok(RuleOutput::Exception(format!(
+            "The following staged files exceed {} and are not stored with Git LFS:\n{}\n\
+            Track them with Git LFS:\n\
+            git lfs install\n\
+            git lfs track \"*.ext\"\n\
+            git add .gitattributes <file>",
+            Self::format_size(threshold),
+            report
+        )))
+    }
+
+    fn try_fix(&self) -> Result<bool, Box<BGitError>> {
+        let repo = self.open_repo()?;
+        let workdir = match repo.workdir() {
+            Some(workdir) => workdir.to_path_buf(),
+            None => return Ok(false),
+        };
+        let large_files = self
+            .find_large_staged_files(&repo, Self::threshold(&repo))
+            .map_err(|e| self.rule_error("Failed to inspect staged files", e))?;
+
+        // Both `git lfs` and the re-staging below go through the git CLI
+        if !program_exists("git") {
+            eprintln!("git is not installed, install it and run bgit again");
+            return Ok(false);
+        }
+        if !Self::is_lfs_installed() {
+            eprintln!(
+                "Git LFS is not installed, install it from https://git-lfs.com and run `git lfs install`"
+            );
+            return Ok(false);
+        }
+
+        let mut all_fixed = true;
+        let mut to_restage = Vec::new();
+        for file in large_files {
+            if file.lfs_tracked {
+                to_restage.push(file.path);
+                continue;
+            }
+
+            let mut options = vec![format!("Track only {}", file.path)];
+            let extension_pattern = Self::lfs_pattern(&file.path, true);
+            if extension_pattern.starts_with("*.") {
+                options.insert(0, format!("Track all {} files", extension_pattern));
+            }
+            options.push(String::from("Skip"));
+
+            let selection = Select::with_theme(&ColorfulTheme::default())
+                .with_prompt(format!(
+                    "{} is {}, how should it be stored with Git LFS?",
+                    file.path,
+                    Self::format_size(file.size)
+                ))
+                .default(0)
+                .items(&options)
+                .interact()
+                .map_err(|e| self.rule_error("Input Error", e))?;
+
+            if selection == options.len() - 1 {
+                all_fixed = false;
+                continue;
+            }
+            let by_extension = options.len() == 3 && selection == 0;
+            let pattern = Self::lfs_pattern(&file.path, by_extension);
+            Self::add_lfs_pattern(&workdir, &pattern)
+                .map_err(|e| self.rule_error("Failed to update .gitattributes", e))?;
+            to_restage.push(file.path);
+        }
+
+        if to_restage.is_empty() {
+            return Ok(all_fixed);
+        }
+
+        if workdir.join(".gitattributes").exists() {
+            let mut index = repo
+                .index()
+                .map_err(|e| self.rule_error("Failed to get repository index", e))?;
+            index
+                .add_path(Path::new(".gitattributes"))
+                .and_then(|_| index.write())
+                .map_err(|e| self.rule_error("Failed to stage .gitattributes", e))?;
+        }
+
+        // libgit2 does not run the LFS clean filter, so the files have to go through git itself
+        let restaged = Command::new("git")
+            .current_dir(&workdir)
+            .args(["add", "--"])
+            .args(&to_restage)
+            .status()
+            .map_err(|e| self.rule_error("Failed to execute git add", e))?;
+
+        Ok(all_fixed && restaged.success())
+    }
+}
+
+impl GitLfsRule {
+    fn open_repo(&self) -> Result<Repository, Box<BGitError>> {
+        let cwd = env::current_dir().map_err(|e| self.rule_error("Failed to get cwd", e))?;
+        Repository::discover(cwd).map_err(|e| self.rule_error("Failed to open repository", e))
+    }
+
+    fn threshold(repo: &Repository) -> u64 {
+        BGitConfig::load(repo)
+            .get_i64("lfs.threshold")
+            .and_then(|threshold| u64::try_from(threshold).ok())
+            .unwrap_or(DEFAULT_LFS_THRESHOLD_BYTES)
+    }
+
+    fn find_large_staged_files(
+        &self,
+        repo: &Repository,
+        threshold: u64,
+    ) -> Result<Vec<LargeStagedFile>, git2::Error> {
+        let head_tree = match repo.head() {
+            Ok(head) => Some(head.peel_to_tree()?),
+            Err(_) => None,
+        };
+        let index = repo.index()?;
+        let diff = repo.diff_tree_to_index(head_tree.as_ref(), Some(&index), None)?;
+        let odb = repo.odb()?;
+
+        let mut large_files = Vec::new();
+        for delta in diff.deltas() {
+            if delta.status() == Delta::Deleted {
+                continue;
+            }
+            let new_file = delta.new_file();
+            let path = match new_file.path() {
+                Some(path) => path,
+                None => continue,
+            };
+            // Read only the object header, large blobs don't need to be loaded
+            let size = match odb.read_header(new_file.id()) {
+                Ok((size, _)) => size as u64,
+                Err(_) => continue,
+            };
+            if size <= threshold {
+                continue;
+            }
+
+            let filter = repo.get_attr(path, "filter", AttrCheckFlags::INDEX_THEN_FILE)?;
+            large_files.push(LargeStagedFile {
+                path: path.to_string_lossy().to_string(),
+                size,
+                lfs_tracked: filter == Some("lfs"),
+            });
+        }
+
+        Ok(large_files)
+    }
+
+    /// `*.ext` for the whole file type, or an anchored exact path otherwise
+    fn lfs_pattern(path: &str, by_extension: bool) -> String {
+        let extension = Path::new(path)
+            .extension()
+            .map(|extension| extension.to_string_lossy());
+        let pattern = match extension {
+            Some(extension) if by_extension => format!("*.{}", extension),
+            _ => format!("/{}", path),
+        };
+        // Same escaping as `git lfs track`, gitattributes patterns can't contain spaces
+        pattern.replace(' ', "[[:space:]]")
+    }
+
+    fn add_lfs_pattern(workdir: &Path, pattern: &str) -> io::Result<()> {
+        let attributes_path = workdir.join(".gitattributes");
+        let existing = fs::read_to_string(&attributes_path).unwrap_or_default();
+        let already_tracked = existing.lines().any(|line| {
+            let mut fields = line.split_whitespace();
+            fields.next() == Some(pattern) && fields.any(|field| field == "filter=lfs")
+        });
+        if already_tracked {
+            return Ok(());
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&attributes_path)?;
+        if !existing.is_empty() && !existing.ends_with('\n') {
+            writeln!(file)?;
+        }
+        writeln!(file, "{} {}", pattern, LFS_ATTRIBUTES)
+    }
+
+    fn is_lfs_installed() -> bool {
+        Command::new("git")
+            .args(["lfs", "version"])
+            .output()
+            .map(|output| output.status.success())
+            .unwrap_or(false)
+    }
+
+    fn format_size(size: u64) -> String {
+        format!("{:.1} MB", size as f64 / (1024.0 * 1024.0))
+    }
+
+    fn rule_error(&self, name: &str, error: impl std::fmt::Display) -> Box<BGitError> {
+        Box::new(BGitError::new(
+            name,
+            &error.to_string(),
+            BGitErrorWorkflowType::Rules,
+            NO_STEP,
+            NO_EVENT,
+            self.get_name(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn stage_file(temp_dir: &TempDir, repo: &Repository, name: &str, size: usize) {
+        fs::write(temp_dir.path().join(name), vec![b'x'; size]).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(name)).unwrap();
+        index.write().unwrap();
+    }
+
+    #[test]
+    fn test_rule_creation() {
+        let rule = GitLfsRule::new();
+        assert_eq!(rule.get_name(), "RULE_git-lfs");
+        assert_eq!(rule.get_level(), RuleLevel::Warning);
+    }
+
+    #[test]
+    fn test_large_staged_files_detection() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        stage_file(&temp_dir, &repo, "small.txt", 10);
+        stage_file(&temp_dir, &repo, "design.psd", 2048);
+        stage_file(&temp_dir, &repo, "video.mp4", 4096);
+        GitLfsRule::add_lfs_pattern(temp_dir.path(), "*.mp4").unwrap();
+
+        let rule = GitLfsRule::new();
+        let large_files = rule.find_large_staged_files(&repo, 1024).unwrap();
+        assert_eq!(
+            large_files,
+            vec![
+                LargeStagedFile {
+                    path: String::from("design.psd"),
+                    size: 2048,
+                    lfs_tracked: false,
+                },
+                LargeStagedFile {
+                    path: String::from("video.mp4"),
+                    size: 4096,
+                    lfs_tracked: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lfs_pattern_and_gitattributes() {
+        assert_eq!(GitLfsRule::lfs_pattern("art/cover.psd", true), "*.psd");
+        assert_eq!(
+            GitLfsRule::lfs_pattern("art/my cover.psd", false),
+            "/art/my[[:space:]]cover.psd"
+        );
+        assert_eq!(GitLfsRule::lfs_pattern("assets/blob", true), "/assets/blob");
+
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join(".gitattributes"), "*.sh text eol=lf").unwrap();
+        GitLfsRule::add_lfs_pattern(temp_dir.path(), "*.psd").unwrap();
+        GitLfsRule::add_lfs_pattern(temp_dir.path(), "*.psd").unwrap();
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join(".gitattributes")).unwrap(),
+            "*.sh text eol=lf\n*.psd filter=lfs diff=lfs merge=lfs -text\n"
+        );
+    }
+}
@@ -1,12 +1,32 @@
-use crate::bgit_error::BGitError;
+use crate::bgit_config::BGitConfig;
+use crate::bgit_error::{BGitError, BGitErrorWorkflowType, NO_EVENT, NO_STEP};
 use crate::rules::{Rule, RuleLevel, RuleOutput};
-use std::process::Command;
+use git2::{Delta, ObjectType, Oid, Repository, Sort};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Default limit of 100 MiB as specified in the docs.
+/// Can be overridden with `repo-size.limit` (e.g. `500m`) in `.bgit/config`
+const DEFAULT_SIZE_LIMIT_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Number of blobs listed in the report, `repo-size.largestBlobs` in `.bgit/config`
+const DEFAULT_LARGEST_BLOBS: usize = 10;
+
+#[derive(Debug, PartialEq)]
+struct LargeBlob {
+    oid: Oid,
+    size: u64,
+    /// Path and commit that first added the blob, `None` when no ref reaches it
+    introduced: Option<(String, Oid)>,
+}
 
 pub(crate) struct BigRepoSizeRule {
     name: String,
     description: String,
     level: RuleLevel,
-    size_limit_mib: u64, // Size limit in MiB
 }
 
 impl Rule for BigRepoSizeRule {
@@ -17,7 +37,6 @@ impl Rule for BigRepoSizeRule {
                 "Warn when a repository's total size exceeds a configurable threshold",
             ),
             level: RuleLevel::Warning,
-            size_limit_mib: 100, // Default 100 MiB as specified in the docs
         }
     }
 
@@ -34,107 +53,274 @@ impl Rule for BigRepoSizeRule {
     }
 
     fn check(&self) -> Result<RuleOutput, Box<BGitError>> {
-        let repo_size = self.get_repo_size()?;
-
-        if repo_size > self.size_limit_mib {
-            let warning_msg = format!(
-                "Repository size ({} MiB) exceeds configured limit of {} MiB.\n\
-                Consider using Git LFS or removing large files using git filter-repo.\n\
-                Suggested fixes:\n\
-                1. Move large assets to Git LFS:\n\
-                   git lfs install\n\
-                   git lfs track \"*.bin\"\n\
-                   git add .gitattributes\n\
-                2. Remove large files from history using git filter-repo\n\
-                3. Increase size limit if this is intentional",
-                repo_size, self.size_limit_mib
-            );
-            Ok(RuleOutput::Exception(warning_msg))
-        } else {
-            Ok(RuleOutput::Success)
+        let repo = self.open_repo()?;
+        let size_limit = Self::size_limit(&repo);
+        let repo_size = self.get_repo_size(&repo)?;
+
+        if repo_size <= size_limit {
+            return Ok(RuleOutput::Success);
         }
+
+        let largest_blobs = self
+            .find_largest_blobs(&repo, Self::largest_blobs_count(&repo))
+            .map_err(|e| self.rule_error("Failed to inspect object database", e))?;
+        let report = largest_blobs
+            .iter()
+            .map(|blob| match &blob.introduced {
+                Some((path, commit)) => format!(
+                    "  {:>10}  {} (added in {})",
+                    Self::format_size(blob.size),
+                    path,
+                    Self::commit_summary(&repo, *commit)
+                ),
+                None => format!(
+                    "  {:>10}  {} (not reachable from any ref)",
+                    Self::format_size(blob.size),
+                    blob.oid
+                ),
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        Ok(RuleOutput::Exception(format!(
+            "Repository size ({}) exceeds configured limit of {}.\n\
+            Largest files in history:\n{}\n\
+            Suggested fixes:\n\
+            1. Move large assets to Git LFS:\n\
+               git lfs install\n\
+               git lfs track \"*.bin\"\n\
+               git add .gitattributes\n\
+            2. Remove large files from history using git filter-repo:\n\
+               git filter-repo --path <path> --invert-paths\n\
+            3. Increase repo-size.limit in .bgit/config if this is intentional",
+            Self::format_size(repo_size),
+            Self::format_size(size_limit),
+            report
+        )))
     }
 
     fn try_fix(&self) -> Result<bool, Box<BGitError>> {
-        // Cannot auto-fix as it requires user intervention
+        // Cannot auto-fix as it requires user intervention, the report is all it can offer
+        if let RuleOutput::Exception(report) = self.check()? {
+            eprintln!("Warning: {}", report);
+        }
         Ok(false)
     }
 }
 
 impl BigRepoSizeRule {
-    fn get_repo_size(&self) -> Result<u64, Box<BGitError>> {
-        let output = Command::new("git")
-            .args(["count-objects", "-vH"])
-            .output()
-            .map_err(|e| {
-                Box::new(BGitError::new(
-                    "Failed to execute git count-objects command",
-                    &e.to_string(),
-                    crate::bgit_error::BGitErrorWorkflowType::Rules,
-                    crate::bgit_error::NO_STEP,
-                    crate::bgit_error::NO_EVENT,
-                    self.get_name(),
-                ))
-            })?;
-
-        let output_str = String::from_utf8_lossy(&output.stdout);
-
-        // Parse the size-pack line to get repository size
-        for line in output_str.lines() {
-            if line.starts_with("size-pack:") {
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                if parts.len() >= 2 {
-                    // Convert size to MiB, handling different units
-                    let size_str = parts[1].to_string();
-                    let unit = if parts.len() > 2 { parts[2] } else { "MiB" };
-
-                    let numeric_size = size_str.parse::<f64>().map_err(|e| {
-                        Box::new(BGitError::new(
-                            "Failed to parse repository size",
-                            &e.to_string(),
-                            crate::bgit_error::BGitErrorWorkflowType::Rules,
-                            crate::bgit_error::NO_STEP,
-                            crate::bgit_error::NO_EVENT,
-                            self.get_name(),
-                        ))
-                    })?;
-
-                    // Convert to MiB based on unit
-                    let size_in_mib = match unit {
-                        "KiB" => numeric_size / 1024.0,
-                        "MiB" => numeric_size,
-                        "GiB" => numeric_size * 1024.0,
-                        _ => numeric_size, // Assume MiB if unit is unknown
-                    };
-
-                    return Ok(size_in_mib as u64);
+    fn open_repo(&self) -> Result<Repository, Box<BGitError>> {
+        let cwd = env::current_dir().map_err(|e| self.rule_error("Failed to get cwd", e))?;
+        Repository::discover(cwd).map_err(|e| self.rule_error("Failed to open repository", e))
+    }
+
+    fn size_limit(repo: &Repository) -> u64 {
+        BGitConfig::load(repo)
+            .get_i64("repo-size.limit")
+            .and_then(|limit| u64::try_from(limit).ok())
+            .unwrap_or(DEFAULT_SIZE_LIMIT_BYTES)
+    }
+
+    fn largest_blobs_count(repo: &Repository) -> usize {
+        BGitConfig::load(repo)
+            .get_i64("repo-size.largestBlobs")
+            .and_then(|count| usize::try_from(count).ok())
+            .unwrap_or(DEFAULT_LARGEST_BLOBS)
+    }
+
+    /// On-disk size of the object database in bytes: pack files and loose objects,
+    /// the same as `size-pack` plus `size` of `git count-objects -v`
+    fn get_repo_size(&self, repo: &Repository) -> Result<u64, Box<BGitError>> {
+        Self::objects_size(&repo.path().join("objects"))
+            .map_err(|e| self.rule_error("Failed to compute repository size", e))
+    }
+
+    fn objects_size(objects_dir: &Path) -> io::Result<u64> {
+        let mut size = 0;
+        for entry in fs::read_dir(objects_dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            let is_loose_dir = name.len() == 2 && name.chars().all(|c| c.is_ascii_hexdigit());
+            if name == "pack" {
+                for pack in fs::read_dir(entry.path())? {
+                    let pack = pack?;
+                    if pack
+                        .path()
+                        .extension()
+                        .is_some_and(|extension| extension == "pack")
+                    {
+                        size += pack.metadata()?.len();
+                    }
+                }
+            } else if is_loose_dir {
+                for object in fs::read_dir(entry.path())? {
+                    size += object?.metadata()?.len();
+                }
+            }
+        }
+        Ok(size)
+    }
+
+    /// The `count` largest blobs of the object database, with the path and
+    /// commit that introduced them
+    fn find_largest_blobs(
+        &self,
+        repo: &Repository,
+        count: usize,
+    ) -> Result<Vec<LargeBlob>, git2::Error> {
+        let odb = repo.odb()?;
+        let mut blobs = Vec::new();
+        odb.foreach(|oid| {
+            if let Ok((size, ObjectType::Blob)) = odb.read_header(*oid) {
+                blobs.push((*oid, size as u64));
+            }
+            true
+        })?;
+        // Packed and loose copies of the same object are reported separately
+        blobs.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        blobs.dedup_by_key(|(oid, _)| *oid);
+        blobs.truncate(count);
+
+        let mut introduced = self.find_introducing_commits(
+            repo,
+            &blobs.iter().map(|(oid, _)| *oid).collect::<Vec<Oid>>(),
+        )?;
+        Ok(blobs
+            .into_iter()
+            .map(|(oid, size)| LargeBlob {
+                oid,
+                size,
+                introduced: introduced.remove(&oid),
+            })
+            .collect())
+    }
+
+    /// Walks all refs from the oldest commit on and records the first commit whose
+    /// diff against its first parent adds one of the blobs
+    fn find_introducing_commits(
+        &self,
+        repo: &Repository,
+        blobs: &[Oid],
+    ) -> Result<HashMap<Oid, (String, Oid)>, git2::Error> {
+        let mut introduced = HashMap::new();
+        if blobs.is_empty() {
+            return Ok(introduced);
+        }
+
+        let mut revwalk = repo.revwalk()?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+        revwalk.push_glob("*")?;
+        if repo.head().is_ok() {
+            revwalk.push_head()?;
+        }
+
+        for commit_id in revwalk {
+            let commit = repo.find_commit(commit_id?)?;
+            let parent_tree = match commit.parent(0) {
+                Ok(parent) => Some(parent.tree()?),
+                Err(_) => None,
+            };
+            let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+            for delta in diff.deltas() {
+                if delta.status() == Delta::Deleted {
+                    continue;
+                }
+                let new_file = delta.new_file();
+                if !blobs.contains(&new_file.id()) || introduced.contains_key(&new_file.id()) {
+                    continue;
+                }
+                if let Some(path) = new_file.path() {
+                    introduced.insert(
+                        new_file.id(),
+                        (path.to_string_lossy().to_string(), commit.id()),
+                    );
                 }
             }
+            if introduced.len() == blobs.len() {
+                break;
+            }
+        }
+
+        Ok(introduced)
+    }
+
+    fn commit_summary(repo: &Repository, commit_id: Oid) -> String {
+        let short_id = commit_id.to_string()[..7].to_owned();
+        match repo.find_commit(commit_id) {
+            Ok(commit) => format!("{} \"{}\"", short_id, commit.summary().unwrap_or_default()),
+            Err(_) => short_id,
         }
+    }
 
-        Err(Box::new(BGitError::new(
-            "Failed to parse repository size",
-            "Could not find size-pack in git count-objects output",
-            crate::bgit_error::BGitErrorWorkflowType::Rules,
-            crate::bgit_error::NO_STEP,
-            crate::bgit_error::NO_EVENT,
+    fn format_size(size: u64) -> String {
+        format!("{:.1} MiB", size as f64 / (1024.0 * 1024.0))
+    }
+
+    fn rule_error(&self, name: &str, error: impl std::fmt::Display) -> Box<BGitError> {
+        Box::new(BGitError::new(
+            name,
+            &error.to_string(),
+            BGitErrorWorkflowType::Rules,
+            NO_STEP,
+            NO_EVENT,
             self.get_name(),
-        )))
+        ))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::commit_file;
+    use tempfile::TempDir;
 
     #[test]
     fn test_rule_creation() {
         let rule = BigRepoSizeRule::new();
         assert_eq!(rule.get_name(), "RULE_big-repo-size");
         assert_eq!(rule.get_level(), RuleLevel::Warning);
-        assert_eq!(rule.size_limit_mib, 100);
     }
 
-    // Additional tests could be added here to test size parsing
-    // and threshold checking using mock git commands
+    #[test]
+    fn test_size_limit_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        assert_eq!(BigRepoSizeRule::size_limit(&repo), 100 * 1024 * 1024);
+        assert_eq!(BigRepoSizeRule::largest_blobs_count(&repo), 10);
+
+        fs::create_dir_all(temp_dir.path().join(".bgit")).unwrap();
+        fs::write(
+            temp_dir.path().join(".bgit/config"),
+            "[repo-size]\n\tlimit = 1g\n\tlargestBlobs = 3\n",
+        )
+        .unwrap();
+        assert_eq!(BigRepoSizeRule::size_limit(&repo), 1024 * 1024 * 1024);
+        assert_eq!(BigRepoSizeRule::largest_blobs_count(&repo), 3);
+    }
+
+    #[test]
+    fn test_find_largest_blobs() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        let first = commit_file(&repo, "small.txt", [b'x'; 10], "Add small.txt");
+        let second = commit_file(&repo, "big.bin", [b'x'; 4096], "Add big.bin");
+        commit_file(&repo, "medium.bin", [b'x'; 1024], "Add medium.bin");
+
+        let rule = BigRepoSizeRule::new();
+        let repo_size = rule.get_repo_size(&repo).unwrap();
+        // Loose objects are compressed, so the repository is smaller than its content
+        assert!(repo_size > 0 && repo_size < 10 + 4096 + 1024);
+
+        let blobs = rule.find_largest_blobs(&repo, 2).unwrap();
+        assert_eq!(blobs.len(), 2);
+        assert_eq!(blobs[0].size, 4096);
+        assert_eq!(blobs[0].introduced, Some((String::from("big.bin"), second)));
+        assert_eq!(blobs[1].size, 1024);
+
+        let blobs = rule.find_largest_blobs(&repo, 3).unwrap();
+        assert_eq!(
+            blobs[2].introduced,
+            Some((String::from("small.txt"), first))
+        );
+    }
 }
@@ -1,6 +1,58 @@
-use crate::bgit_error::BGitError;
+use crate::bgit_error::{BGitError, BGitErrorWorkflowType, NO_EVENT, NO_STEP};
 use crate::rules::{Rule, RuleLevel, RuleOutput};
-use std::process::Command;
+use dialoguer::Confirm;
+use git2::{Delta, Repository};
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Project types with their own build artifacts in the catalog
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ProjectType {
+    Any,
+    Node,
+    Rust,
+    Python,
+}
+
+/// Artifacts that are almost never committed on purpose. A trailing `/` matches a
+/// directory anywhere in the path, `*.ext` a file extension and anything else a file name.
+const IGNORE_CATALOG: [(ProjectType, &str); 16] = [
+    (ProjectType::Any, ".env"),
+    (ProjectType::Any, ".DS_Store"),
+    (ProjectType::Any, "Thumbs.db"),
+    (ProjectType::Any, ".idea/"),
+    (ProjectType::Any, ".vscode/"),
+    (ProjectType::Any, "*.swp"),
+    (ProjectType::Node, "node_modules/"),
+    (ProjectType::Node, "npm-debug.log"),
+    (ProjectType::Node, ".next/"),
+    (ProjectType::Rust, "target/"),
+    (ProjectType::Python, "__pycache__/"),
+    (ProjectType::Python, "*.pyc"),
+    (ProjectType::Python, ".venv/"),
+    (ProjectType::Python, ".pytest_cache/"),
+    (ProjectType::Python, "*.egg-info/"),
+    (ProjectType::Python, ".mypy_cache/"),
+];
+
+/// Marker files in the repository root that identify the project type
+const PROJECT_MARKERS: [(ProjectType, &str); 6] = [
+    (ProjectType::Node, "package.json"),
+    (ProjectType::Rust, "Cargo.toml"),
+    (ProjectType::Python, "pyproject.toml"),
+    (ProjectType::Python, "requirements.txt"),
+    (ProjectType::Python, "setup.py"),
+    (ProjectType::Python, "Pipfile"),
+];
+
+#[derive(Debug, PartialEq)]
+struct IgnoreViolation {
+    path: String,
+    /// Catalog pattern the path matched, `None` when `.gitignore` already ignores it
+    catalog_pattern: Option<&'static str>,
+}
 
 pub(crate) struct FileNotGitIgnoredRule {
     name: String,
@@ -30,141 +82,352 @@ impl Rule for FileNotGitIgnoredRule {
     }
 
     fn check(&self) -> Result<RuleOutput, Box<BGitError>> {
-        // Get staged files
-        let staged_files = self.get_staged_files()?;
-        let mut violations = Vec::new();
+        let repo = self.open_repo()?;
+        let violations = self
+            .find_violations(&repo)
+            .map_err(|e| self.rule_error("Failed to inspect staged files", e))?;
 
-        // Check each staged file against gitignore rules
-        for file in staged_files {
-            if self.should_be_ignored(&file)? {
-                violations.push(file);
-            }
+        if violations.is_empty() {
+            return Ok(RuleOutput::Success);
         }
 
-        if !violations.is_empty() {
-            let error_msg = format!(
-                "The following files are staged but should be gitignored according to your .gitignore rules:\n{}\n\
-                Please update .gitignore if needed and unstage these files using:\n\
-                git reset HEAD <file>",
-                violations.join("\n")
-            );
-            Ok(RuleOutput::Exception(error_msg))
-        } else {
-            Ok(RuleOutput::Success)
-        }
+        let report = violations
+            .iter()
+            .map(|violation| match violation.catalog_pattern {
+                Some(pattern) => format!("  {} (matches '{}')", violation.path, pattern),
+                None => format!("  {} (ignored by .gitignore)", violation.path),
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        Ok(RuleOutput::Exception(format!(
+            "The following files are staged but should be gitignored:\n{}\n\
+            Please update .gitignore if needed and unstage these files using:\n\
+            git reset HEAD <file>",
+            report
+        )))
     }
 
     fn try_fix(&self) -> Result<bool, Box<BGitError>> {
-        // Cannot auto-fix as it requires user intervention
-        Ok(false)
+        let repo = self.open_repo()?;
+        let violations = self
+            .find_violations(&repo)
+            .map_err(|e| self.rule_error("Failed to inspect staged files", e))?;
+        if violations.is_empty() {
+            return Ok(true);
+        }
+
+        let mut patterns: Vec<&str> = violations
+            .iter()
+            .filter_map(|violation| violation.catalog_pattern)
+            .collect();
+        patterns.sort();
+        patterns.dedup();
+
+        let prompt = if patterns.is_empty() {
+            format!("Unstage {} ignored file(s)?", violations.len())
+        } else {
+            format!(
+                "Add {} to .gitignore and unstage {} file(s)?",
+                patterns.join(", "),
+                violations.len()
+            )
+        };
+        let confirmed = Confirm::new()
+            .with_prompt(prompt)
+            .default(true)
+            .interact()
+            .map_err(|e| self.rule_error("Input Error", e))?;
+        if !confirmed {
+            return Ok(false);
+        }
+
+        let workdir = repo.workdir().ok_or_else(|| {
+            self.rule_error("Failed to fix", "Repository has no working directory")
+        })?;
+        Self::add_ignore_patterns(workdir, &patterns)
+            .map_err(|e| self.rule_error("Failed to update .gitignore", e))?;
+
+        let paths: Vec<&str> = violations
+            .iter()
+            .map(|violation| violation.path.as_str())
+            .collect();
+        self.unstage(&repo, &paths, !patterns.is_empty())
+            .map_err(|e| self.rule_error("Failed to unstage files", e))?;
+
+        Ok(true)
     }
 }
 
 impl FileNotGitIgnoredRule {
-    fn get_staged_files(&self) -> Result<Vec<String>, Box<BGitError>> {
-        let output = Command::new("git")
-            .args(["diff", "--cached", "--name-only"])
-            .output()
-            .map_err(|e| {
-                Box::new(BGitError::new(
-                    "Failed to execute git command",
-                    &e.to_string(),
-                    crate::bgit_error::BGitErrorWorkflowType::Rules,
-                    crate::bgit_error::NO_STEP,
-                    crate::bgit_error::NO_EVENT,
-                    self.get_name(),
-                ))
-            })?;
-
-        let files = String::from_utf8_lossy(&output.stdout)
-            .split('\n')
-            .filter(|s| !s.is_empty())
-            .map(String::from)
+    fn open_repo(&self) -> Result<Repository, Box<BGitError>> {
+        let cwd = env::current_dir().map_err(|e| self.rule_error("Failed to get cwd", e))?;
+        Repository::discover(cwd).map_err(|e| self.rule_error("Failed to open repository", e))
+    }
+
+    fn find_violations(&self, repo: &Repository) -> Result<Vec<IgnoreViolation>, git2::Error> {
+        let head_tree = match repo.head() {
+            Ok(head) => Some(head.peel_to_tree()?),
+            Err(_) => None,
+        };
+        let index = repo.index()?;
+        let mut diff = repo.diff_tree_to_index(head_tree.as_ref(), Some(&index), None)?;
+        diff.find_similar(None)?;
+        let project_types = repo
+            .workdir()
+            .map(Self::detect_project_types)
+            .unwrap_or_else(|| vec![ProjectType::Any]);
+
+        let mut violations = Vec::new();
+        for delta in diff.deltas() {
+            // Files tracked before they were ignored stay tracked, only new paths are checked
+            if !matches!(
+                delta.status(),
+                Delta::Added | Delta::Renamed | Delta::Copied
+            ) {
+                continue;
+            }
+            let path = match delta.new_file().path() {
+                Some(path) => path,
+                None => continue,
+            };
+
+            let catalog_pattern = Self::catalog_match(path, &project_types);
+            if catalog_pattern.is_some() || repo.status_should_ignore(path)? {
+                violations.push(IgnoreViolation {
+                    path: path.to_string_lossy().to_string(),
+                    catalog_pattern,
+                });
+            }
+        }
+
+        Ok(violations)
+    }
+
+    fn detect_project_types(workdir: &Path) -> Vec<ProjectType> {
+        let mut project_types = vec![ProjectType::Any];
+        for (project_type, marker) in PROJECT_MARKERS {
+            if workdir.join(marker).exists() && !project_types.contains(&project_type) {
+                project_types.push(project_type);
+            }
+        }
+        project_types
+    }
+
+    fn catalog_match(path: &Path, project_types: &[ProjectType]) -> Option<&'static str> {
+        let components: Vec<String> = path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().to_string())
             .collect();
+        let (file_name, directories) = components.split_last()?;
 
-        Ok(files)
+        IGNORE_CATALOG
+            .iter()
+            .filter(|(project_type, _)| project_types.contains(project_type))
+            .map(|(_, pattern)| *pattern)
+            .find(|pattern| match pattern.strip_suffix('/') {
+                Some(directory) => directories
+                    .iter()
+                    .any(|name| Self::name_matches(directory, name)),
+                None => Self::name_matches(pattern, file_name),
+            })
     }
 
-    fn should_be_ignored(&self, file: &str) -> Result<bool, Box<BGitError>> {
-        let output = Command::new("git")
-            .args(["check-ignore", file])
-            .output()
-            .map_err(|e| {
-                Box::new(BGitError::new(
-                    "Failed to execute git check-ignore",
-                    &e.to_string(),
-                    crate::bgit_error::BGitErrorWorkflowType::Rules,
-                    crate::bgit_error::NO_STEP,
-                    crate::bgit_error::NO_EVENT,
-                    self.get_name(),
-                ))
-            })?;
+    /// Exact name, or `*suffix` for patterns like `*.pyc`
+    fn name_matches(pattern: &str, name: &str) -> bool {
+        match pattern.strip_prefix('*') {
+            Some(suffix) => name.ends_with(suffix),
+            None => pattern == name,
+        }
+    }
 
-        // If the file should be ignored, git check-ignore exits with status 0
-        Ok(output.status.success())
+    fn add_ignore_patterns(workdir: &Path, patterns: &[&str]) -> io::Result<()> {
+        let gitignore_path = workdir.join(".gitignore");
+        let existing = fs::read_to_string(&gitignore_path).unwrap_or_default();
+        let missing: Vec<&&str> = patterns
+            .iter()
+            .filter(|pattern| !existing.lines().any(|line| line.trim() == **pattern))
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&gitignore_path)?;
+        if !existing.is_empty() && !existing.ends_with('\n') {
+            writeln!(file)?;
+        }
+        for pattern in missing {
+            writeln!(file, "{}", pattern)?;
+        }
+        Ok(())
+    }
+
+    /// `git reset HEAD <paths>`, and stage `.gitignore` when it was changed
+    fn unstage(
+        &self,
+        repo: &Repository,
+        paths: &[&str],
+        stage_gitignore: bool,
+    ) -> Result<(), git2::Error> {
+        let head = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+        match head {
+            // Entries missing from HEAD are removed from the index
+            Some(head) => repo.reset_default(Some(head.as_object()), paths)?,
+            None => {
+                let mut index = repo.index()?;
+                for path in paths {
+                    index.remove_path(Path::new(path))?;
+                }
+                index.write()?;
+            }
+        }
+
+        if stage_gitignore {
+            let mut index = repo.index()?;
+            index.add_path(Path::new(".gitignore"))?;
+            index.write()?;
+        }
+        Ok(())
+    }
+
+    fn rule_error(&self, name: &str, error: impl std::fmt::Display) -> Box<BGitError> {
+        Box::new(BGitError::new(
+            name,
+            &error.to_string(),
+            BGitErrorWorkflowType::Rules,
+            NO_STEP,
+            NO_EVENT,
+            self.get_name(),
+        ))
     }
 }
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs::File;
-    use std::io::Write;
+    use crate::test_util::commit_index;
+    use tempfile::TempDir;
 
-    fn setup_test_env() -> Result<(), std::io::Error> {
-        // Create .gitignore file
-        let mut gitignore = File::create(".gitignore")?;
-        writeln!(gitignore, ".env")?;
+    fn stage_file(temp_dir: &TempDir, repo: &Repository, name: &str, content: &str) {
+        let path = temp_dir.path().join(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(&path, content).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(name)).unwrap();
+        index.write().unwrap();
+    }
 
-        // Add and commit .gitignore first
-        Command::new("git").args(["add", ".gitignore"]).output()?;
-        Command::new("git")
-            .args(["commit", "-m", "Add .gitignore"])
-            .output()?;
+    #[test]
+    fn test_ignored_file_detection() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        fs::write(temp_dir.path().join(".gitignore"), "*.log\n").unwrap();
+        // Staged on purpose, index.add_path doesn't look at .gitignore
+        stage_file(&temp_dir, &repo, "debug.log", "trace");
+        stage_file(&temp_dir, &repo, "src/main.rs", "fn main() {}");
 
-        // Create .env file
-        let mut env_file = File::create(".env")?;
-        writeln!(env_file, "SECRET_KEY=test123")?;
+        let rule = FileNotGitIgnoredRule::new();
+        let violations = rule.find_violations(&repo).unwrap();
+        assert_eq!(
+            violations,
+            vec![IgnoreViolation {
+                path: String::from("debug.log"),
+                catalog_pattern: None,
+            }]
+        );
+    }
 
-        // Remove .env from index if tracked
-        let _ = Command::new("git")
-            .args(["rm", "--cached", ".env"])
-            .output();
+    #[test]
+    fn test_only_new_paths_checked() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        stage_file(&temp_dir, &repo, "debug.log", "trace");
+        stage_file(&temp_dir, &repo, "notes.txt", "notes");
+        commit_index(&repo, "init");
 
-        // Stage the .env file
-        Command::new("git").args(["add", ".env"]).output()?;
+        fs::write(temp_dir.path().join(".gitignore"), "*.log\n").unwrap();
+        stage_file(&temp_dir, &repo, "debug.log", "more trace");
+        let rule = FileNotGitIgnoredRule::new();
+        assert!(rule.find_violations(&repo).unwrap().is_empty());
 
-        Ok(())
+        fs::rename(
+            temp_dir.path().join("notes.txt"),
+            temp_dir.path().join("notes.log"),
+        )
+        .unwrap();
+        let mut index = repo.index().unwrap();
+        index.remove_path(Path::new("notes.txt")).unwrap();
+        index.add_path(Path::new("notes.log")).unwrap();
+        index.write().unwrap();
+        let violations = rule.find_violations(&repo).unwrap();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].path, "notes.log");
     }
 
-    fn cleanup_test_env() {
-        // Clean up test files
-        let _ = std::fs::remove_file(".env");
-        let _ = Command::new("git")
-            .args(["reset", "--hard", "HEAD"])
-            .output();
-        let _ = Command::new("git").args(["clean", "-fd"]).output();
+    #[test]
+    fn test_catalog_by_project_type() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        stage_file(&temp_dir, &repo, ".env", "SECRET_KEY=test123");
+        stage_file(&temp_dir, &repo, ".env.example", "SECRET_KEY=");
+        stage_file(&temp_dir, &repo, "target/debug/app", "binary");
+
+        let rule = FileNotGitIgnoredRule::new();
+        let violations = rule.find_violations(&repo).unwrap();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].catalog_pattern, Some(".env"));
+
+        stage_file(&temp_dir, &repo, "Cargo.toml", "[package]");
+        let violations = rule.find_violations(&repo).unwrap();
+        assert_eq!(violations.len(), 2);
+        assert_eq!(violations[1].path, "target/debug/app");
+        assert_eq!(violations[1].catalog_pattern, Some("target/"));
+
+        let python = [ProjectType::Any, ProjectType::Python];
+        assert_eq!(
+            FileNotGitIgnoredRule::catalog_match(
+                Path::new("pkg/__pycache__/mod.cpython-312.pyc"),
+                &python
+            ),
+            Some("__pycache__/")
+        );
+        assert_eq!(
+            FileNotGitIgnoredRule::catalog_match(Path::new("pkg/mod.pyc"), &python),
+            Some("*.pyc")
+        );
     }
 
     #[test]
-    fn test_ignored_file_detection() {
-        // Setup test environment
-        setup_test_env().expect("Failed to setup test environment");
+    fn test_fix_unstages_and_ignores() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        stage_file(&temp_dir, &repo, "README.md", "readme");
+        commit_index(&repo, "init");
+
+        stage_file(&temp_dir, &repo, "node_modules/left-pad/index.js", "x");
+        stage_file(&temp_dir, &repo, "package.json", "{}");
 
         let rule = FileNotGitIgnoredRule::new();
-        match rule.check() {
-            Ok(RuleOutput::Exception(_)) => {
-                // Test passed - rule detected the violation
-                assert!(true);
-            }
-            Ok(RuleOutput::Success) => {
-                panic!("Rule should have detected staged .env file");
-            }
-            _ => {
-                panic!("Unexpected rule output");
-            }
-        }
+        let violations = rule.find_violations(&repo).unwrap();
+        assert_eq!(violations.len(), 1);
+
+        FileNotGitIgnoredRule::add_ignore_patterns(temp_dir.path(), &["node_modules/"]).unwrap();
+        FileNotGitIgnoredRule::add_ignore_patterns(temp_dir.path(), &["node_modules/"]).unwrap();
+        rule.unstage(&repo, &["node_modules/left-pad/index.js"], true)
+            .unwrap();
 
-        // Cleanup
-        cleanup_test_env();
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join(".gitignore")).unwrap(),
+            "node_modules/\n"
+        );
+        assert!(rule.find_violations(&repo).unwrap().is_empty());
+        let index = repo.index().unwrap();
+        assert!(index.get_path(Path::new(".gitignore"), 0).is_some());
+        assert!(index.get_path(Path::new("package.json"), 0).is_some());
     }
 }
@@ -0,0 +1,197 @@
+use crate::bgit_config::BGitConfig;
+use crate::bgit_error::{BGitError, BGitErrorWorkflowType, NO_EVENT, NO_STEP};
+use crate::events::git_branch::GitBranch;
+use crate::events::AtomicEvent;
+use crate::rules::a23_branch_naming::BranchPolicy;
+use crate::rules::{Rule, RuleLevel, RuleOutput};
+use crate::util::wildcard_match;
+use dialoguer::{theme::ColorfulTheme, Input};
+use git2::{Branch, BranchType, Repository};
+use std::env;
+
+/// Always protected, more globs are added with `protected-branches.pattern` in `.bgit/config`
+const DEFAULT_PROTECTED_BRANCHES: [&str; 3] = ["main", "master", "release/*"];
+
+pub(crate) struct ProtectedBranchRule {
+    name: String,
+    description: String,
+    level: RuleLevel,
+}
+
+impl Rule for ProtectedBranchRule {
+    fn new() -> Self {
+        ProtectedBranchRule {
+            name: String::from("RULE_protect-main-branch"),
+            description: String::from(
+                "Block direct commits and pushes to main, master, release/* and configured branches",
+            ),
+            level: RuleLevel::Error,
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_description(&self) -> &str {
+        &self.description
+    }
+
+    fn get_level(&self) -> RuleLevel {
+        self.level.clone()
+    }
+
+    fn check(&self) -> Result<RuleOutput, Box<BGitError>> {
+        let repo = self.open_repo()?;
+        let branch = match Self::current_branch(&repo) {
+            Some(branch) => branch,
+            // Unborn or detached HEAD, nothing to protect
+            None => return Ok(RuleOutput::Success),
+        };
+
+        match Self::protected_pattern(&repo, &branch) {
+            None => Ok(RuleOutput::Success),
+            Some(pattern) => Ok(RuleOutput::Exception(format!(
+                "Branch '{}' is protected (matches '{}'), changes must go through a feature branch.\n\
+                Create one from the current HEAD with: git switch -c feat/<name>",
+                branch, pattern
+            ))),
+        }
+    }
+
+    fn try_fix(&self) -> Result<bool, Box<BGitError>> {
+        let repo = self.open_repo()?;
+        let branch_name: String = Input::with_theme(&ColorfulTheme::default())
+            .with_prompt("Name of the feature branch to move your staged changes to")
+            .validate_with(|input: &String| -> Result<(), String> {
+                let input = input.trim();
+                if !Branch::name_is_valid(input).unwrap_or(false) {
+                    return Err(format!("'{}' is not a valid branch name", input));
+                }
+                if let Some(pattern) = Self::protected_pattern(&repo, input) {
+                    return Err(format!("'{}' is protected as well ({})", input, pattern));
+                }
+                // The default prefixes only warn, they don't restrict the name picked here
+                if let Some(policy) = BranchPolicy::load(&repo).ok().filter(|p| p.is_configured()) {
+                    if !policy.allows(input) {
+                        return Err(format!("'{}' doesn't match {}", input, policy.describe()));
+                    }
+                }
+                if repo.find_branch(input, BranchType::Local).is_ok() {
+                    return Err(format!("Branch '{}' already exists", input));
+                }
+                Ok(())
+            })
+            .interact_text()
+            .map_err(|e| self.rule_error("Input Error", e))?;
+        let branch_name = branch_name.trim();
+
+        let mut git_branch = GitBranch::new();
+        git_branch.set_branch_name(branch_name);
+        if !git_branch.execute()? {
+            return Ok(false);
+        }
+
+        // The new branch points at the same commit, so moving HEAD leaves the index
+        // and working tree untouched and the staged changes come along
+        repo.set_head(&format!("refs/heads/{}", branch_name))
+            .map_err(|e| self.rule_error("Failed to switch branch", e))?;
+        println!("Switched to new branch '{}'", branch_name);
+        Ok(true)
+    }
+}
+
+impl ProtectedBranchRule {
+    fn open_repo(&self) -> Result<Repository, Box<BGitError>> {
+        let cwd = env::current_dir().map_err(|e| self.rule_error("Failed to get cwd", e))?;
+        Repository::discover(cwd).map_err(|e| self.rule_error("Failed to open repository", e))
+    }
+
+    /// Short name of the checked out branch, `None` for a detached or unborn HEAD
+    fn current_branch(repo: &Repository) -> Option<String> {
+        let head = repo.head().ok()?;
+        if !head.is_branch() {
+            return None;
+        }
+        head.shorthand().map(String::from)
+    }
+
+    /// The first protected pattern `branch` matches
+    pub(crate) fn protected_pattern(repo: &Repository, branch: &str) -> Option<String> {
+        DEFAULT_PROTECTED_BRANCHES
+            .iter()
+            .map(|pattern| pattern.to_string())
+            .chain(BGitConfig::load(repo).get_all("protected-branches.pattern"))
+            .find(|pattern| wildcard_match(pattern, branch))
+    }
+
+    fn rule_error(&self, name: &str, error: impl std::fmt::Display) -> Box<BGitError> {
+        Box::new(BGitError::new(
+            name,
+            &error.to_string(),
+            BGitErrorWorkflowType::Rules,
+            NO_STEP,
+            NO_EVENT,
+            self.get_name(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::commit_index;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_protected_pattern() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        assert_eq!(
+            ProtectedBranchRule::protected_pattern(&repo, "main"),
+            Some(String::from("main"))
+        );
+        assert_eq!(
+            ProtectedBranchRule::protected_pattern(&repo, "release/1.2"),
+            Some(String::from("release/*"))
+        );
+        assert_eq!(
+            ProtectedBranchRule::protected_pattern(&repo, "feat/main"),
+            None
+        );
+        assert_eq!(
+            ProtectedBranchRule::protected_pattern(&repo, "develop"),
+            None
+        );
+
+        fs::create_dir_all(temp_dir.path().join(".bgit")).unwrap();
+        fs::write(
+            temp_dir.path().join(".bgit/config"),
+            "[protected-branches]\n\tpattern = develop\n\tpattern = hotfix-*\n",
+        )
+        .unwrap();
+        assert_eq!(
+            ProtectedBranchRule::protected_pattern(&repo, "develop"),
+            Some(String::from("develop"))
+        );
+        assert_eq!(
+            ProtectedBranchRule::protected_pattern(&repo, "hotfix-42"),
+            Some(String::from("hotfix-*"))
+        );
+    }
+
+    #[test]
+    fn test_current_branch() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        assert_eq!(ProtectedBranchRule::current_branch(&repo), None);
+
+        let commit = commit_index(&repo, "Initial");
+        let branch = ProtectedBranchRule::current_branch(&repo).unwrap();
+        assert!(ProtectedBranchRule::protected_pattern(&repo, &branch).is_some());
+
+        repo.set_head_detached(commit).unwrap();
+        assert_eq!(ProtectedBranchRule::current_branch(&repo), None);
+    }
+}
@@ -0,0 +1,346 @@
+use crate::bgit_config::BGitConfig;
+use crate::bgit_error::{BGitError, BGitErrorWorkflowType, NO_EVENT, NO_STEP};
+use crate::rules::{Rule, RuleLevel, RuleOutput};
+use dialoguer::Editor;
+use git2::Repository;
+use regex::Regex;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// Used unless `commit-message.type` is set in `.bgit/config`
+const DEFAULT_TYPES: [&str; 11] = [
+    "feat", "fix", "docs", "style", "refactor", "perf", "test", "build", "ci", "chore", "revert",
+];
+
+/// Maximum length of the `type(scope)!: subject` line, `commit-message.maxHeaderLength`
+const DEFAULT_MAX_HEADER_LENGTH: usize = 72;
+
+/// `type(scope)!: subject`
+const HEADER_PATTERN: &str =
+    r"^(?P<type>[^()!:\s]+)(?:\((?P<scope>[^()]*)\))?(?P<breaking>!)?: (?P<subject>.*)$";
+
+/// `Token: value` or `Token #value`, tokens use `-` instead of spaces except `BREAKING CHANGE`
+const TRAILER_PATTERN: &str =
+    r"^(?P<token>(?i:BREAKING CHANGE)|[A-Za-z0-9][A-Za-z0-9-]*)(?:: | #)\S";
+
+/// Allowed types and scopes, read from the `[commit-message]` section of `.bgit/config`.
+/// An empty scope list allows any scope.
+struct MessagePolicy {
+    types: Vec<String>,
+    scopes: Vec<String>,
+    max_header_length: usize,
+}
+
+impl MessagePolicy {
+    fn load(repo: &Repository) -> Self {
+        let config = BGitConfig::load(repo);
+        let mut types = config.get_all("commit-message.type");
+        if types.is_empty() {
+            types = DEFAULT_TYPES.iter().map(|t| t.to_string()).collect();
+        }
+        MessagePolicy {
+            types,
+            scopes: config.get_all("commit-message.scope"),
+            max_header_length: config
+                .get_i64("commit-message.maxHeaderLength")
+                .and_then(|length| usize::try_from(length).ok())
+                .unwrap_or(DEFAULT_MAX_HEADER_LENGTH),
+        }
+    }
+}
+
+/// Checks the message of the commit being created, which `git_commit` writes to
+/// `.git/COMMIT_EDITMSG` before running its commit message rules, the same file
+/// git hands to the `commit-msg` hook
+pub(crate) struct ConventionalCommitMessageRule {
+    name: String,
+    description: String,
+    level: RuleLevel,
+}
+
+impl Rule for ConventionalCommitMessageRule {
+    fn new() -> Self {
+        ConventionalCommitMessageRule {
+            name: String::from("RULE_conventional-commit-message"),
+            description: String::from(
+                "Commit messages must follow the Conventional Commits format",
+            ),
+            level: RuleLevel::Error,
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_description(&self) -> &str {
+        &self.description
+    }
+
+    fn get_level(&self) -> RuleLevel {
+        self.level.clone()
+    }
+
+    fn check(&self) -> Result<RuleOutput, Box<BGitError>> {
+        let repo = self.open_repo()?;
+        let message = match fs::read_to_string(message_path(&repo)) {
+            Ok(message) => cleanup_message(&message),
+            // No commit in progress, nothing to check
+            Err(_) => return Ok(RuleOutput::Success),
+        };
+
+        let problems = Self::lint(&message, &MessagePolicy::load(&repo));
+        if problems.is_empty() {
+            return Ok(RuleOutput::Success);
+        }
+        Ok(RuleOutput::Exception(format!(
+            "Commit message does not follow Conventional Commits:\n{}\n\
+            Expected: <type>(<scope>)!: <subject>, e.g. feat(parser): support arrays",
+            problems
+                .iter()
+                .map(|problem| format!("  - {}", problem))
+                .collect::<Vec<String>>()
+                .join("\n")
+        )))
+    }
+
+    fn try_fix(&self) -> Result<bool, Box<BGitError>> {
+        let repo = self.open_repo()?;
+        let path = message_path(&repo);
+        let message = cleanup_message(&fs::read_to_string(&path).unwrap_or_default());
+        let policy = MessagePolicy::load(&repo);
+
+        let mut template = format!(
+            "{}\n\n# Fix the commit message, lines starting with '#' are ignored.\n\
+            # Allowed types: {}\n",
+            message,
+            policy.types.join(", ")
+        );
+        if !policy.scopes.is_empty() {
+            template.push_str(&format!("# Allowed scopes: {}\n", policy.scopes.join(", ")));
+        }
+        for problem in Self::lint(&message, &policy) {
+            template.push_str(&format!("# - {}\n", problem));
+        }
+
+        let edited = Editor::new()
+            .require_save(true)
+            .edit(&template)
+            .map_err(|e| self.rule_error("Input Error", e))?;
+        match edited {
+            Some(edited) => {
+                fs::write(&path, format!("{}\n", cleanup_message(&edited)))
+                    .map_err(|e| self.rule_error("Failed to write commit message", e))?;
+                Ok(true)
+            }
+            // Editor closed without saving
+            None => Ok(false),
+        }
+    }
+}
+
+impl ConventionalCommitMessageRule {
+    fn open_repo(&self) -> Result<Repository, Box<BGitError>> {
+        let cwd = env::current_dir().map_err(|e| self.rule_error("Failed to get cwd", e))?;
+        Repository::discover(cwd).map_err(|e| self.rule_error("Failed to open repository", e))
+    }
+
+    /// Every problem of an already cleaned up message, empty when it is valid
+    fn lint(message: &str, policy: &MessagePolicy) -> Vec<String> {
+        let header_regex = Regex::new(HEADER_PATTERN).expect("valid header pattern");
+        let trailer_regex = Regex::new(TRAILER_PATTERN).expect("valid trailer pattern");
+        let mut problems = Vec::new();
+
+        let lines: Vec<&str> = message.lines().collect();
+        let header = match lines.first() {
+            Some(header) => *header,
+            None => return vec![String::from("Commit message is empty")],
+        };
+
+        match header_regex.captures(header) {
+            None => problems.push(format!(
+                "Header '{}' is not '<type>(<scope>)!: <subject>'",
+                header
+            )),
+            Some(captures) => {
+                let commit_type = &captures["type"];
+                if !policy.types.iter().any(|allowed| allowed == commit_type) {
+                    problems.push(format!(
+                        "Type '{}' is not one of: {}",
+                        commit_type,
+                        policy.types.join(", ")
+                    ));
+                }
+                if let Some(scope) = captures.name("scope").map(|scope| scope.as_str()) {
+                    if scope.trim().is_empty() {
+                        problems.push(String::from("Scope in parentheses is empty"));
+                    } else if !policy.scopes.is_empty()
+                        && !policy.scopes.iter().any(|allowed| allowed == scope)
+                    {
+                        problems.push(format!(
+                            "Scope '{}' is not one of: {}",
+                            scope,
+                            policy.scopes.join(", ")
+                        ));
+                    }
+                }
+                if captures["subject"].trim().is_empty() {
+                    problems.push(String::from("Subject after the colon is empty"));
+                }
+            }
+        }
+
+        let header_length = header.chars().count();
+        if header_length > policy.max_header_length {
+            problems.push(format!(
+                "Header is {} characters long, the limit is {}",
+                header_length, policy.max_header_length
+            ));
+        }
+
+        if lines.get(1).is_some_and(|line| !line.trim().is_empty()) {
+            problems.push(String::from(
+                "The header must be followed by a blank line before the body",
+            ));
+        }
+
+        // Trailers are the last paragraph after the header, when it starts like one
+        let paragraphs: Vec<&[&str]> = lines[1..]
+            .split(|line| line.trim().is_empty())
+            .filter(|paragraph| !paragraph.is_empty())
+            .collect();
+        if let Some(trailers) = paragraphs
+            .last()
+            .filter(|paragraph| trailer_regex.is_match(paragraph[0]))
+        {
+            for line in trailers.iter() {
+                // Indented lines continue the previous trailer's value
+                if line.starts_with(char::is_whitespace) {
+                    continue;
+                }
+                match trailer_regex.captures(line) {
+                    None => problems.push(format!(
+                        "Trailer '{}' is not 'Token: value', use '-' instead of spaces in the token",
+                        line
+                    )),
+                    Some(captures) => {
+                        let token = &captures["token"];
+                        let is_breaking = token.eq_ignore_ascii_case("breaking change")
+                            || token.eq_ignore_ascii_case("breaking-change");
+                        if is_breaking && token != "BREAKING CHANGE" && token != "BREAKING-CHANGE" {
+                            problems.push(format!("Trailer '{}' must be upper case", token));
+                        }
+                    }
+                }
+            }
+        }
+
+        problems
+    }
+
+    fn rule_error(&self, name: &str, error: impl std::fmt::Display) -> Box<BGitError> {
+        Box::new(BGitError::new(
+            name,
+            &error.to_string(),
+            BGitErrorWorkflowType::Rules,
+            NO_STEP,
+            NO_EVENT,
+            self.get_name(),
+        ))
+    }
+}
+
+/// `.git/COMMIT_EDITMSG`, holding the message of the commit being created
+pub(crate) fn message_path(repo: &Repository) -> PathBuf {
+    repo.path().join("COMMIT_EDITMSG")
+}
+
+/// What `git commit --cleanup=strip` does: drop `#` comment lines and trailing
+/// whitespace, squeeze blank lines and trim blank lines around the message
+pub(crate) fn cleanup_message(message: &str) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+    for line in message.lines() {
+        if line.starts_with('#') {
+            continue;
+        }
+        let line = line.trim_end();
+        if line.is_empty() && lines.last().is_none_or(|last| last.is_empty()) {
+            continue;
+        }
+        lines.push(line);
+    }
+    while lines.last().is_some_and(|last| last.is_empty()) {
+        lines.pop();
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> MessagePolicy {
+        MessagePolicy {
+            types: DEFAULT_TYPES.iter().map(|t| t.to_string()).collect(),
+            scopes: Vec::new(),
+            max_header_length: DEFAULT_MAX_HEADER_LENGTH,
+        }
+    }
+
+    #[test]
+    fn test_valid_messages() {
+        let policy = policy();
+        for message in [
+            "feat: add login form",
+            "fix(parser)!: reject trailing commas",
+            "docs(readme): explain rules\n\nLonger description\nover two lines.",
+            "feat(api)!: drop v1 endpoints\n\nBody text.\n\nBREAKING CHANGE: v1 is gone\nRefs #123\nSigned-off-by: Dev <dev@example.com>",
+            "chore: bump deps\n\nReviewed-by: Someone\n  continued value",
+        ] {
+            assert!(
+                ConventionalCommitMessageRule::lint(message, &policy).is_empty(),
+                "{}",
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_messages() {
+        let mut policy = policy();
+        let lint = |message: &str, policy: &MessagePolicy| {
+            ConventionalCommitMessageRule::lint(message, policy)
+        };
+
+        assert_eq!(lint("", &policy), vec!["Commit message is empty"]);
+        assert_eq!(lint("Update stuff", &policy).len(), 1);
+        assert_eq!(lint("feature: add login", &policy).len(), 1);
+        assert_eq!(lint("fix(): typo", &policy).len(), 1);
+        assert_eq!(lint("fix:typo", &policy).len(), 1);
+        assert_eq!(lint("fix: typo\nbody right away", &policy).len(), 1);
+        assert_eq!(lint(&format!("fix: {}", "a".repeat(80)), &policy).len(), 1);
+        assert_eq!(
+            lint("fix: typo\n\nSigned-off by: Dev", &policy),
+            Vec::<String>::new(),
+            "a paragraph that doesn't start like a trailer is body text"
+        );
+        assert_eq!(
+            lint("fix: typo\n\nRefs: #1\nSigned off by: Dev", &policy).len(),
+            1
+        );
+        assert_eq!(lint("fix: typo\n\nBreaking change: yes", &policy).len(), 1);
+
+        policy.scopes = vec![String::from("cli")];
+        assert!(lint("fix(cli): typo", &policy).is_empty());
+        assert_eq!(lint("fix(core): typo", &policy).len(), 1);
+    }
+
+    #[test]
+    fn test_cleanup_message() {
+        assert_eq!(
+            cleanup_message("\n\nfeat: x  \n\n\n\nbody\n# comment\n\n"),
+            "feat: x\n\nbody"
+        );
+    }
+}
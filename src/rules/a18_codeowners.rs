@@ -0,0 +1,380 @@
+use crate::bgit_error::{BGitError, BGitErrorWorkflowType, NO_EVENT, NO_STEP};
+use crate::remote_url::GitHosting;
+use crate::rules::a03_github_username::local_username;
+use crate::rules::{override_reason, Rule, RuleLevel, RuleOutput};
+use colored::Colorize;
+use dialoguer::Confirm;
+use git2::Repository;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Searched in this order like GitHub does, the first file found is used
+const CODEOWNERS_LOCATIONS: [&str; 3] = [".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+struct CodeOwnersEntry {
+    regex: Regex,
+    owners: Vec<String>,
+}
+
+/// Parsed `CODEOWNERS`, where the last matching pattern decides the owners
+struct CodeOwners {
+    location: &'static str,
+    entries: Vec<CodeOwnersEntry>,
+}
+
+impl CodeOwners {
+    fn load(repo: &Repository) -> Option<Self> {
+        let workdir = repo.workdir()?;
+        CODEOWNERS_LOCATIONS.into_iter().find_map(|location| {
+            let content = fs::read_to_string(workdir.join(location)).ok()?;
+            Some(Self::parse(location, &content))
+        })
+    }
+
+    /// Lines GitHub would reject, like `!negation` or `[ranges]`, are skipped
+    fn parse(location: &'static str, content: &str) -> Self {
+        let mut entries = Vec::new();
+        for line in content.lines() {
+            let line = line.trim();
+            // `[Section]` headers are GitLab syntax
+            if line.is_empty() || line.starts_with('#') || line.starts_with('[') {
+                continue;
+            }
+            let mut tokens = line.split_whitespace();
+            let pattern = match tokens.next() {
+                Some(pattern) => pattern.replace("\\#", "#"),
+                None => continue,
+            };
+            let owners = tokens
+                .take_while(|token| !token.starts_with('#'))
+                .map(String::from)
+                .collect();
+            if let Some(regex) = Self::pattern_regex(&pattern) {
+                entries.push(CodeOwnersEntry { regex, owners });
+            }
+        }
+        CodeOwners { location, entries }
+    }
+
+    /// gitignore style globs as GitHub reads them: a pattern with a `/` other than a trailing
+    /// one is relative to the root, otherwise it matches at any depth. A trailing `/` or a
+    /// plain name owns everything below the directory, while `docs/*` only owns the files
+    /// directly in `docs`.
+    fn pattern_regex(pattern: &str) -> Option<Regex> {
+        if pattern.starts_with('!') || pattern.contains('[') || pattern.contains(']') {
+            return None;
+        }
+        let anchored = pattern.trim_end_matches('/').contains('/');
+        let directory = pattern.ends_with('/');
+        let pattern = pattern.trim_start_matches('/').trim_end_matches('/');
+        if pattern.is_empty() {
+            return None;
+        }
+
+        let mut regex = String::from(if anchored { "^" } else { "^(?:.*/)?" });
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            match chars[i] {
+                '*' if chars.get(i + 1) == Some(&'*') => {
+                    if chars.get(i + 2) == Some(&'/') {
+                        regex.push_str("(?:.*/)?");
+                        i += 3;
+                    } else {
+                        regex.push_str(".*");
+                        i += 2;
+                    }
+                    continue;
+                }
+                '*' => regex.push_str("[^/]*"),
+                '?' => regex.push_str("[^/]"),
+                c => regex.push_str(&regex::escape(&c.to_string())),
+            }
+            i += 1;
+        }
+
+        let last_segment = pattern.rsplit('/').next().unwrap_or(pattern);
+        if directory {
+            regex.push_str("/.*");
+        } else if !last_segment.contains(['*', '?']) {
+            regex.push_str("(?:/.*)?");
+        }
+        regex.push('$');
+        Regex::new(&regex).ok()
+    }
+
+    /// Owners of `path`, empty when the last matching pattern has none or nothing matches
+    fn owners_of(&self, path: &str) -> &[String] {
+        self.entries
+            .iter()
+            .rev()
+            .find(|entry| entry.regex.is_match(path))
+            .map(|entry| entry.owners.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+/// Staged path whose owners don't include the committer
+#[derive(Debug, PartialEq)]
+struct OwnedPath {
+    path: String,
+    owners: Vec<String>,
+}
+
+pub(crate) struct CodeOwnersRule {
+    name: String,
+    description: String,
+    level: RuleLevel,
+    /// Set once the user accepted committing to owned paths
+    confirmed: AtomicBool,
+}
+
+impl Rule for CodeOwnersRule {
+    fn new() -> Self {
+        CodeOwnersRule {
+            name: String::from("RULE_codeowners-protected-paths"),
+            description: String::from(
+                "Confirm staged changes to paths owned by others in CODEOWNERS",
+            ),
+            level: RuleLevel::Error,
+            confirmed: AtomicBool::new(false),
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_description(&self) -> &str {
+        &self.description
+    }
+
+    fn get_level(&self) -> RuleLevel {
+        self.level.clone()
+    }
+
+    fn check(&self) -> Result<RuleOutput, Box<BGitError>> {
+        if self.confirmed.load(Ordering::Relaxed) {
+            return Ok(RuleOutput::Success);
+        }
+        let repo = self.open_repo()?;
+        let (location, violations) = self.find_violations(&repo)?;
+        if violations.is_empty() {
+            return Ok(RuleOutput::Success);
+        }
+        Ok(RuleOutput::Exception(format!(
+            "Staged changes touch paths owned by others in {}:\n{}\n\
+            Contact the owners, or rerun with --reason \"<why>\" to continue",
+            location,
+            Self::format_owners(&violations)
+        )))
+    }
+
+    fn try_fix(&self) -> Result<bool, Box<BGitError>> {
+        let repo = self.open_repo()?;
+        let (location, violations) = self.find_violations(&repo)?;
+        println!(
+            "Staged changes touch paths owned by others in {}, contact the owners:\n{}",
+            location,
+            Self::format_owners(&violations)
+        );
+
+        let confirmed = match override_reason() {
+            Some(reason) => {
+                println!("Continuing with reason: {}", reason.bright_blue());
+                true
+            }
+            None => Confirm::new()
+                .with_prompt("Commit changes to these paths anyway?")
+                .default(false)
+                .interact()
+                .map_err(|e| self.rule_error("Input Error", e))?,
+        };
+        self.confirmed.store(confirmed, Ordering::Relaxed);
+        Ok(confirmed)
+    }
+}
+
+impl CodeOwnersRule {
+    fn open_repo(&self) -> Result<Repository, Box<BGitError>> {
+        let cwd = env::current_dir().map_err(|e| self.rule_error("Failed to get cwd", e))?;
+        Repository::discover(cwd).map_err(|e| self.rule_error("Failed to open repository", e))
+    }
+
+    /// Staged paths with owners the committer is not one of, and the CODEOWNERS file used
+    fn find_violations(
+        &self,
+        repo: &Repository,
+    ) -> Result<(&'static str, Vec<OwnedPath>), Box<BGitError>> {
+        let codeowners = match CodeOwners::load(repo) {
+            Some(codeowners) => codeowners,
+            None => return Ok(("", Vec::new())),
+        };
+        let identities = Self::committer_identities(repo);
+
+        let index = repo
+            .index()
+            .map_err(|e| self.rule_error("Failed to read index", e))?;
+        let head_tree = match repo.head() {
+            Ok(head) => Some(
+                head.peel_to_tree()
+                    .map_err(|e| self.rule_error("Failed to read HEAD tree", e))?,
+            ),
+            Err(_) => None,
+        };
+        let diff = repo
+            .diff_tree_to_index(head_tree.as_ref(), Some(&index), None)
+            .map_err(|e| self.rule_error("Failed to diff staged changes", e))?;
+
+        let mut violations: Vec<OwnedPath> = Vec::new();
+        for delta in diff.deltas() {
+            // Renames and deletions touch the old path as well
+            for file in [delta.old_file(), delta.new_file()] {
+                let path = match file.path() {
+                    Some(path) => path.to_string_lossy().replace('\\', "/"),
+                    None => continue,
+                };
+                if violations.iter().any(|owned| owned.path == path) {
+                    continue;
+                }
+                let owners = codeowners.owners_of(&path);
+                let is_owner = owners.iter().any(|owner| {
+                    identities
+                        .iter()
+                        .any(|identity| identity.eq_ignore_ascii_case(owner))
+                });
+                if !owners.is_empty() && !is_owner {
+                    violations.push(OwnedPath {
+                        path,
+                        owners: owners.to_vec(),
+                    });
+                }
+            }
+        }
+        Ok((codeowners.location, violations))
+    }
+
+    /// `user.email` and `@login` on GitHub and GitLab, as they appear in CODEOWNERS.
+    /// Team owners (`@org/team`) can't be resolved without the API.
+    fn committer_identities(repo: &Repository) -> Vec<String> {
+        let mut identities = Vec::new();
+        if let Ok(email) = repo
+            .config()
+            .and_then(|config| config.get_string("user.email"))
+        {
+            identities.push(email);
+        }
+        for hosting in [GitHosting::GitHub, GitHosting::GitLab] {
+            if let Some(username) = local_username(repo, hosting) {
+                identities.push(format!("@{}", username));
+            }
+        }
+        identities
+    }
+
+    /// One line per owner group, listing its paths
+    fn format_owners(violations: &[OwnedPath]) -> String {
+        let mut by_owners: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+        for owned in violations {
+            by_owners
+                .entry(owned.owners.join(" "))
+                .or_default()
+                .push(owned.path.as_str());
+        }
+        by_owners
+            .iter()
+            .map(|(owners, paths)| format!("  {}\n    {}", owners, paths.join("\n    ")))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn rule_error(&self, name: &str, error: impl std::fmt::Display) -> Box<BGitError> {
+        Box::new(BGitError::new(
+            name,
+            &error.to_string(),
+            BGitErrorWorkflowType::Rules,
+            NO_STEP,
+            NO_EVENT,
+            self.get_name(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_github_pattern_semantics() {
+        let codeowners = CodeOwners::parse(
+            "CODEOWNERS",
+            "# Default owners\n\
+            *       @global-owner\n\
+            *.js    @js-owner #inline comment\n\
+            docs/*  docs@example.com\n\
+            apps/   @octocat\n\
+            /scripts/ @doctocat @octocat\n\
+            **/logs @logger\n\
+            /apps/github\n\
+            !ignored @nobody\n",
+        );
+
+        let owners = |path: &str| codeowners.owners_of(path).join(" ");
+        assert_eq!(owners("README.md"), "@global-owner");
+        assert_eq!(owners("src/app/index.js"), "@js-owner");
+        assert_eq!(owners("docs/getting-started.md"), "docs@example.com");
+        assert_eq!(owners("docs/build-app/troubleshooting.md"), "@global-owner");
+        assert_eq!(owners("web/apps/main.rs"), "@octocat");
+        assert_eq!(owners("scripts/deploy/run.sh"), "@doctocat @octocat");
+        assert_eq!(owners("lib/scripts/run.sh"), "@global-owner");
+        assert_eq!(owners("build/logs/out.txt"), "@logger");
+        assert_eq!(owners("apps/github/main.rs"), "");
+        assert_eq!(owners("ignored"), "@global-owner");
+    }
+
+    #[test]
+    fn test_find_violations() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        repo.config()
+            .unwrap()
+            .set_str("user.email", "me@example.com")
+            .unwrap();
+        fs::create_dir_all(temp_dir.path().join(".github")).unwrap();
+        fs::write(
+            temp_dir.path().join(".github/CODEOWNERS"),
+            "/core/ @org/core-team alice@example.com\n/mine/ me@example.com\n",
+        )
+        .unwrap();
+        // Lower in the search order, so it is ignored
+        fs::write(temp_dir.path().join("CODEOWNERS"), "* @everyone\n").unwrap();
+
+        let mut index = repo.index().unwrap();
+        for path in ["core/lib.rs", "mine/lib.rs", "other.rs"] {
+            let file = temp_dir.path().join(path);
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(&file, "fn main() {}\n").unwrap();
+            index.add_path(Path::new(path)).unwrap();
+        }
+        index.write().unwrap();
+
+        let rule = CodeOwnersRule::new();
+        let (location, violations) = rule.find_violations(&repo).unwrap();
+        assert_eq!(location, ".github/CODEOWNERS");
+        assert_eq!(
+            violations,
+            vec![OwnedPath {
+                path: String::from("core/lib.rs"),
+                owners: vec![
+                    String::from("@org/core-team"),
+                    String::from("alice@example.com")
+                ],
+            }]
+        );
+    }
+}
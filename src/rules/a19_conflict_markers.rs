@@ -0,0 +1,223 @@
+use crate::bgit_error::{BGitError, BGitErrorWorkflowType, NO_EVENT, NO_STEP};
+use crate::rules::{Rule, RuleLevel, RuleOutput};
+use crate::util::added_lines;
+use git2::{Delta, Repository};
+use regex::Regex;
+use std::env;
+
+/// `<<<<<<< ours`, `||||||| base` (diff3 style) and `>>>>>>> theirs`
+const MARKER_PATTERN: &str = r"^(<{7}|\|{7}|>{7})(?: |$)";
+
+/// Also a setext heading underline, only reported next to the other markers
+const SEPARATOR_MARKER: &str = "=======";
+
+#[derive(Debug, PartialEq)]
+struct ConflictFinding {
+    path: String,
+    /// `None` for a conflict entry in the index
+    line: Option<usize>,
+}
+
+pub(crate) struct ConflictMarkersRule {
+    name: String,
+    description: String,
+    level: RuleLevel,
+}
+
+impl Rule for ConflictMarkersRule {
+    fn new() -> Self {
+        ConflictMarkersRule {
+            name: String::from("RULE_no-conflict-markers"),
+            description: String::from(
+                "Refuse commits with unresolved conflicts or staged conflict markers",
+            ),
+            level: RuleLevel::Error,
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_description(&self) -> &str {
+        &self.description
+    }
+
+    fn get_level(&self) -> RuleLevel {
+        self.level.clone()
+    }
+
+    fn check(&self) -> Result<RuleOutput, Box<BGitError>> {
+        let repo = self.open_repo()?;
+        let findings = self
+            .find_conflicts(&repo)
+            .map_err(|e| self.rule_error("Failed to scan staged changes", e))?;
+        if findings.is_empty() {
+            return Ok(RuleOutput::Success);
+        }
+
+        let report = findings
+            .iter()
+            .map(|finding| match finding.line {
+                Some(line) => format!("  {}:{}: conflict marker", finding.path, line),
+                None => format!("  {}: unresolved conflict in the index", finding.path),
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        Ok(RuleOutput::Exception(format!(
+            "Merge conflicts are not resolved:\n{}\n\
+            Resolve them, remove the markers and stage the files again with git add",
+            report
+        )))
+    }
+
+    fn try_fix(&self) -> Result<bool, Box<BGitError>> {
+        // Choosing between both sides of a conflict needs the user
+        Ok(false)
+    }
+}
+
+impl ConflictMarkersRule {
+    fn open_repo(&self) -> Result<Repository, Box<BGitError>> {
+        let cwd = env::current_dir().map_err(|e| self.rule_error("Failed to get cwd", e))?;
+        Repository::discover(cwd).map_err(|e| self.rule_error("Failed to open repository", e))
+    }
+
+    /// Conflict entries of the index first, then markers on added lines of staged text files
+    fn find_conflicts(&self, repo: &Repository) -> Result<Vec<ConflictFinding>, git2::Error> {
+        let marker_regex = Regex::new(MARKER_PATTERN).expect("valid marker pattern");
+        let index = repo.index()?;
+        let mut findings = Vec::new();
+
+        if index.has_conflicts() {
+            for conflict in index.conflicts()? {
+                let conflict = conflict?;
+                let entry = conflict.our.or(conflict.their).or(conflict.ancestor);
+                if let Some(entry) = entry {
+                    let path = String::from_utf8_lossy(&entry.path).to_string();
+                    if !findings
+                        .iter()
+                        .any(|finding: &ConflictFinding| finding.path == path)
+                    {
+                        findings.push(ConflictFinding { path, line: None });
+                    }
+                }
+            }
+        }
+
+        let head_tree = match repo.head() {
+            Ok(head) => Some(head.peel_to_tree()?),
+            Err(_) => None,
+        };
+        let diff = repo.diff_tree_to_index(head_tree.as_ref(), Some(&index), None)?;
+        for (delta_index, delta) in diff.deltas().enumerate() {
+            if matches!(delta.status(), Delta::Deleted | Delta::Conflicted) {
+                continue;
+            }
+            let new_file = delta.new_file();
+            let path = match new_file.path() {
+                Some(path) => path.to_string_lossy().to_string(),
+                None => continue,
+            };
+            match repo.find_blob(new_file.id()) {
+                Ok(blob) if !blob.is_binary() => {}
+                // Binary files and submodules can't hold text markers
+                _ => continue,
+            }
+
+            let lines = added_lines(&diff, delta_index)?;
+            let has_markers = lines.iter().any(|(_, line)| marker_regex.is_match(line));
+            for (line_number, line) in lines {
+                if marker_regex.is_match(&line) || (has_markers && line == SEPARATOR_MARKER) {
+                    findings.push(ConflictFinding {
+                        path: path.clone(),
+                        line: Some(line_number),
+                    });
+                }
+            }
+        }
+
+        Ok(findings)
+    }
+
+    fn rule_error(&self, name: &str, error: impl std::fmt::Display) -> Box<BGitError> {
+        Box::new(BGitError::new(
+            name,
+            &error.to_string(),
+            BGitErrorWorkflowType::Rules,
+            NO_STEP,
+            NO_EVENT,
+            self.get_name(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    fn stage(temp_dir: &TempDir, repo: &Repository, path: &str, content: &str) {
+        fs::write(temp_dir.path().join(path), content).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(path)).unwrap();
+        index.write().unwrap();
+    }
+
+    #[test]
+    fn test_staged_conflict_markers() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        stage(
+            &temp_dir,
+            &repo,
+            "main.rs",
+            "fn main() {\n<<<<<<< HEAD\n    one();\n=======\n    two();\n>>>>>>> feature\n}\n",
+        );
+        stage(&temp_dir, &repo, "README.md", "Title\n=======\n\nText\n");
+
+        let rule = ConflictMarkersRule::new();
+        let lines: Vec<Option<usize>> = rule
+            .find_conflicts(&repo)
+            .unwrap()
+            .into_iter()
+            .map(|finding| {
+                assert_eq!(finding.path, "main.rs");
+                finding.line
+            })
+            .collect();
+        assert_eq!(lines, vec![Some(2), Some(4), Some(6)]);
+    }
+
+    #[test]
+    fn test_index_conflicts() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        stage(&temp_dir, &repo, "lib.rs", "base\n");
+
+        let mut index = repo.index().unwrap();
+        let entries: Vec<_> = (1..=3u16)
+            .map(|stage| {
+                let mut entry = index.get_path(Path::new("lib.rs"), 0).unwrap();
+                entry.flags = stage << 12;
+                entry
+            })
+            .collect();
+        index.remove_path(Path::new("lib.rs")).unwrap();
+        for entry in entries {
+            index.add(&entry).unwrap();
+        }
+        index.write().unwrap();
+
+        let findings = ConflictMarkersRule::new().find_conflicts(&repo).unwrap();
+        assert_eq!(
+            findings,
+            vec![ConflictFinding {
+                path: String::from("lib.rs"),
+                line: None
+            }]
+        );
+    }
+}
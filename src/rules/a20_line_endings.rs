@@ -0,0 +1,382 @@
+use crate::bgit_error::{BGitError, BGitErrorWorkflowType, NO_EVENT, NO_STEP};
+use crate::rules::{Rule, RuleLevel, RuleOutput};
+use dialoguer::Confirm;
+use git2::{AttrCheckFlags, AttrValue, Delta, Repository};
+use std::env;
+use std::fs;
+use std::path::Path;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+const GITATTRIBUTES_LINE: &str = "* text=auto eol=lf";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TextIssue {
+    MixedLineEndings,
+    ByteOrderMark,
+    InvalidUtf8,
+    MissingTrailingNewline,
+}
+
+impl TextIssue {
+    fn describe(&self) -> &'static str {
+        match self {
+            TextIssue::MixedLineEndings => "mixed CRLF and LF line endings",
+            TextIssue::ByteOrderMark => "UTF-8 byte order mark",
+            TextIssue::InvalidUtf8 => "invalid UTF-8",
+            TextIssue::MissingTrailingNewline => "no newline at end of file",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct TextFinding {
+    path: String,
+    issues: Vec<TextIssue>,
+}
+
+pub(crate) struct LineEndingsRule {
+    name: String,
+    description: String,
+    level: RuleLevel,
+}
+
+impl Rule for LineEndingsRule {
+    fn new() -> Self {
+        LineEndingsRule {
+            name: String::from("RULE_line-endings-encoding"),
+            description: String::from(
+                "Staged text files should use consistent line endings and valid UTF-8 without a BOM",
+            ),
+            level: RuleLevel::Warning,
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_description(&self) -> &str {
+        &self.description
+    }
+
+    fn get_level(&self) -> RuleLevel {
+        self.level.clone()
+    }
+
+    fn check(&self) -> Result<RuleOutput, Box<BGitError>> {
+        let repo = self.open_repo()?;
+        let findings = self
+            .find_issues(&repo)
+            .map_err(|e| self.rule_error("Failed to scan staged files", e))?;
+        if findings.is_empty() {
+            return Ok(RuleOutput::Success);
+        }
+        Ok(RuleOutput::Exception(format!(
+            "Staged text files have line ending or encoding problems:\n{}\n\
+            Add `{}` to .gitattributes to normalize line endings for everyone",
+            Self::format_findings(&findings),
+            GITATTRIBUTES_LINE
+        )))
+    }
+
+    fn try_fix(&self) -> Result<bool, Box<BGitError>> {
+        let repo = self.open_repo()?;
+        let findings = self
+            .find_issues(&repo)
+            .map_err(|e| self.rule_error("Failed to scan staged files", e))?;
+        println!(
+            "Staged text files have line ending or encoding problems:\n{}",
+            Self::format_findings(&findings)
+        );
+
+        let mut fixed = false;
+        let fixable = findings.iter().any(|finding| {
+            finding
+                .issues
+                .iter()
+                .any(|issue| *issue != TextIssue::InvalidUtf8)
+        });
+        if fixable
+            && Confirm::new()
+                .with_prompt("Normalize the staged files to LF without BOM and add final newlines?")
+                .default(true)
+                .interact()
+                .map_err(|e| self.rule_error("Input Error", e))?
+        {
+            self.normalize_index(&repo, &findings)
+                .map_err(|e| self.rule_error("Failed to normalize staged files", e))?;
+            println!("Normalized the staged content, the working tree files are unchanged");
+            fixed = true;
+        }
+
+        if !Self::has_gitattributes_policy(&repo)
+            && Confirm::new()
+                .with_prompt(format!(
+                    "Add `{}` to .gitattributes and stage it?",
+                    GITATTRIBUTES_LINE
+                ))
+                .default(true)
+                .interact()
+                .map_err(|e| self.rule_error("Input Error", e))?
+        {
+            Self::add_gitattributes(&repo)
+                .map_err(|e| self.rule_error("Failed to write .gitattributes", e))?;
+            fixed = true;
+        }
+
+        Ok(fixed
+            && findings
+                .iter()
+                .all(|finding| !finding.issues.contains(&TextIssue::InvalidUtf8)))
+    }
+}
+
+impl LineEndingsRule {
+    fn open_repo(&self) -> Result<Repository, Box<BGitError>> {
+        let cwd = env::current_dir().map_err(|e| self.rule_error("Failed to get cwd", e))?;
+        Repository::discover(cwd).map_err(|e| self.rule_error("Failed to open repository", e))
+    }
+
+    /// Staged blobs git treats as text, skipping paths marked `-text`/`binary` in .gitattributes
+    fn find_issues(&self, repo: &Repository) -> Result<Vec<TextFinding>, git2::Error> {
+        let head_tree = match repo.head() {
+            Ok(head) => Some(head.peel_to_tree()?),
+            Err(_) => None,
+        };
+        let index = repo.index()?;
+        let diff = repo.diff_tree_to_index(head_tree.as_ref(), Some(&index), None)?;
+
+        let mut findings = Vec::new();
+        for delta in diff.deltas() {
+            if !matches!(
+                delta.status(),
+                Delta::Added | Delta::Modified | Delta::Renamed | Delta::Copied
+            ) {
+                continue;
+            }
+            let new_file = delta.new_file();
+            let path = match new_file.path() {
+                Some(path) => path,
+                None => continue,
+            };
+            let attribute = |name: &str| {
+                AttrValue::from_string(
+                    repo.get_attr(path, name, AttrCheckFlags::INDEX_ONLY)
+                        .ok()
+                        .flatten(),
+                )
+            };
+            if matches!(attribute("text"), AttrValue::False) {
+                continue;
+            }
+            let blob = match repo.find_blob(new_file.id()) {
+                Ok(blob) if !blob.is_binary() => blob,
+                _ => continue,
+            };
+
+            let mut issues = Self::text_issues(blob.content(), delta.status() == Delta::Added);
+            // Files checked out in another encoding are UTF-8 only in the repository
+            if !matches!(attribute("working-tree-encoding"), AttrValue::Unspecified) {
+                issues.retain(|issue| *issue != TextIssue::ByteOrderMark);
+            }
+            if !issues.is_empty() {
+                findings.push(TextFinding {
+                    path: path.to_string_lossy().to_string(),
+                    issues,
+                });
+            }
+        }
+        Ok(findings)
+    }
+
+    fn text_issues(content: &[u8], added: bool) -> Vec<TextIssue> {
+        let mut issues = Vec::new();
+        let crlf = content.windows(2).filter(|pair| pair == b"\r\n").count();
+        let lf = content.iter().filter(|byte| **byte == b'\n').count();
+        if crlf > 0 && lf > crlf {
+            issues.push(TextIssue::MixedLineEndings);
+        }
+        if content.starts_with(UTF8_BOM) {
+            issues.push(TextIssue::ByteOrderMark);
+        }
+        if std::str::from_utf8(content).is_err() {
+            issues.push(TextIssue::InvalidUtf8);
+        }
+        if added && !content.is_empty() && !content.ends_with(b"\n") {
+            issues.push(TextIssue::MissingTrailingNewline);
+        }
+        issues
+    }
+
+    /// LF line endings, no BOM and a final newline, bytes that aren't UTF-8 are kept
+    fn normalize(content: &[u8]) -> Vec<u8> {
+        let content = content.strip_prefix(UTF8_BOM).unwrap_or(content);
+        let mut normalized = Vec::with_capacity(content.len() + 1);
+        let mut bytes = content.iter().peekable();
+        while let Some(byte) = bytes.next() {
+            if *byte == b'\r' && bytes.peek() == Some(&&b'\n') {
+                continue;
+            }
+            normalized.push(*byte);
+        }
+        if !normalized.is_empty() && !normalized.ends_with(b"\n") {
+            normalized.push(b'\n');
+        }
+        normalized
+    }
+
+    /// Replaces the staged blobs of `findings` with their normalized content
+    fn normalize_index(
+        &self,
+        repo: &Repository,
+        findings: &[TextFinding],
+    ) -> Result<(), git2::Error> {
+        let mut index = repo.index()?;
+        for finding in findings {
+            if finding.issues == [TextIssue::InvalidUtf8] {
+                continue;
+            }
+            let mut entry = match index.get_path(Path::new(&finding.path), 0) {
+                Some(entry) => entry,
+                None => continue,
+            };
+            let content = repo.find_blob(entry.id)?.content().to_vec();
+            let normalized = Self::normalize(&content);
+            if normalized == content {
+                continue;
+            }
+            entry.id = repo.blob(&normalized)?;
+            entry.file_size = normalized.len() as u32;
+            index.add(&entry)?;
+        }
+        index.write()
+    }
+
+    /// A root .gitattributes that already sets `text` for every file
+    fn has_gitattributes_policy(repo: &Repository) -> bool {
+        repo.workdir()
+            .and_then(|workdir| fs::read_to_string(workdir.join(".gitattributes")).ok())
+            .is_some_and(|content| {
+                content.lines().any(|line| {
+                    let mut fields = line.split_whitespace();
+                    fields.next() == Some("*")
+                        && fields.any(|field| field == "text" || field.starts_with("text="))
+                })
+            })
+    }
+
+    fn add_gitattributes(repo: &Repository) -> Result<(), Box<dyn std::error::Error>> {
+        let workdir = repo
+            .workdir()
+            .ok_or("Repository has no working directory")?;
+        let path = workdir.join(".gitattributes");
+        let mut content = fs::read_to_string(&path).unwrap_or_default();
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+        content.push_str(GITATTRIBUTES_LINE);
+        content.push('\n');
+        fs::write(&path, content)?;
+
+        let mut index = repo.index()?;
+        index.add_path(Path::new(".gitattributes"))?;
+        index.write()?;
+        Ok(())
+    }
+
+    fn format_findings(findings: &[TextFinding]) -> String {
+        findings
+            .iter()
+            .map(|finding| {
+                format!(
+                    "  {}: {}",
+                    finding.path,
+                    finding
+                        .issues
+                        .iter()
+                        .map(TextIssue::describe)
+                        .collect::<Vec<&str>>()
+                        .join(", ")
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn rule_error(&self, name: &str, error: impl std::fmt::Display) -> Box<BGitError> {
+        Box::new(BGitError::new(
+            name,
+            &error.to_string(),
+            BGitErrorWorkflowType::Rules,
+            NO_STEP,
+            NO_EVENT,
+            self.get_name(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_text_issues() {
+        assert!(LineEndingsRule::text_issues(b"one\ntwo\n", true).is_empty());
+        assert!(LineEndingsRule::text_issues(b"one\r\ntwo\r\n", true).is_empty());
+        assert!(LineEndingsRule::text_issues(b"no newline", false).is_empty());
+        assert_eq!(
+            LineEndingsRule::text_issues(b"\xEF\xBB\xBFone\r\ntwo\nthree", true),
+            vec![
+                TextIssue::MixedLineEndings,
+                TextIssue::ByteOrderMark,
+                TextIssue::MissingTrailingNewline
+            ]
+        );
+        assert_eq!(
+            LineEndingsRule::text_issues(b"caf\xE9\n", true),
+            vec![TextIssue::InvalidUtf8]
+        );
+        assert_eq!(
+            LineEndingsRule::normalize(b"\xEF\xBB\xBFone\r\ntwo\nthree"),
+            b"one\ntwo\nthree\n"
+        );
+    }
+
+    #[test]
+    fn test_normalize_index() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        fs::write(temp_dir.path().join("mixed.txt"), b"one\r\ntwo\n").unwrap();
+        fs::write(temp_dir.path().join("raw.bin"), b"one\r\ntwo\n").unwrap();
+        fs::write(temp_dir.path().join(".gitattributes"), "*.bin -text\n").unwrap();
+        let mut index = repo.index().unwrap();
+        for path in ["mixed.txt", "raw.bin", ".gitattributes"] {
+            index.add_path(Path::new(path)).unwrap();
+        }
+        index.write().unwrap();
+
+        let rule = LineEndingsRule::new();
+        let findings = rule.find_issues(&repo).unwrap();
+        assert_eq!(
+            findings,
+            vec![TextFinding {
+                path: String::from("mixed.txt"),
+                issues: vec![TextIssue::MixedLineEndings]
+            }]
+        );
+        assert!(!LineEndingsRule::has_gitattributes_policy(&repo));
+
+        rule.normalize_index(&repo, &findings).unwrap();
+        assert!(rule.find_issues(&repo).unwrap().is_empty());
+        // Only the index changes
+        assert_eq!(
+            fs::read(temp_dir.path().join("mixed.txt")).unwrap(),
+            b"one\r\ntwo\n"
+        );
+
+        LineEndingsRule::add_gitattributes(&repo).unwrap();
+        assert!(LineEndingsRule::has_gitattributes_policy(&repo));
+    }
+}
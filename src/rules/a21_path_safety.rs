@@ -0,0 +1,321 @@
+use crate::bgit_error::{BGitError, BGitErrorWorkflowType, NO_EVENT, NO_STEP};
+use crate::rules::{Rule, RuleLevel, RuleOutput};
+use git2::{Delta, Repository};
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+
+/// Device names Windows reserves in every directory, with or without an extension
+const WINDOWS_RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Besides control characters, NTFS rejects these in file names
+const NTFS_ILLEGAL_CHARACTERS: [char; 8] = ['<', '>', ':', '"', '\\', '|', '?', '*'];
+
+/// `MAX_PATH` on Windows without long path support, counted from the repository root
+const MAX_PATH_LENGTH: usize = 260;
+
+#[derive(Debug, PartialEq)]
+enum PathProblem {
+    /// Paths that are the same on case-insensitive file systems
+    CaseCollision(Vec<String>),
+    ReservedName(String),
+    TrailingDotOrSpace(String),
+    IllegalCharacter(String, char),
+    TooLong(String, usize),
+}
+
+impl PathProblem {
+    fn describe(&self) -> String {
+        match self {
+            PathProblem::CaseCollision(paths) => {
+                format!("differ only in case: {}", paths.join(", "))
+            }
+            PathProblem::ReservedName(path) => {
+                format!("{}: reserved device name on Windows", path)
+            }
+            PathProblem::TrailingDotOrSpace(path) => {
+                format!("{}: name ends with a dot or space", path)
+            }
+            PathProblem::IllegalCharacter(path, character) => {
+                format!("{}: {:?} is not allowed on NTFS", path, character)
+            }
+            PathProblem::TooLong(path, length) => format!(
+                "{}: {} characters, longer than {}",
+                path, length, MAX_PATH_LENGTH
+            ),
+        }
+    }
+}
+
+pub(crate) struct PathSafetyRule {
+    name: String,
+    description: String,
+    level: RuleLevel,
+}
+
+impl Rule for PathSafetyRule {
+    fn new() -> Self {
+        PathSafetyRule {
+            name: String::from("RULE_cross-platform-paths"),
+            description: String::from(
+                "Paths in the index must check out on Windows, macOS and Linux",
+            ),
+            level: RuleLevel::Error,
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_description(&self) -> &str {
+        &self.description
+    }
+
+    fn get_level(&self) -> RuleLevel {
+        self.level.clone()
+    }
+
+    fn check(&self) -> Result<RuleOutput, Box<BGitError>> {
+        let repo = self.open_repo()?;
+        let index = repo
+            .index()
+            .map_err(|e| self.rule_error("Failed to read index", e))?;
+        let mut paths: Vec<String> = index
+            .iter()
+            .map(|entry| String::from_utf8_lossy(&entry.path).to_string())
+            .collect();
+        // Conflicted paths have an entry per stage
+        paths.dedup();
+        let new_paths = Self::new_paths(&repo)
+            .map_err(|e| self.rule_error("Failed to diff the index against HEAD", e))?;
+
+        let problems = Self::find_problems(&paths, &new_paths);
+        if problems.is_empty() {
+            return Ok(RuleOutput::Success);
+        }
+        Ok(RuleOutput::Exception(format!(
+            "Paths in the index can't be checked out on every platform:\n{}\n\
+            Rename them with git mv",
+            problems
+                .iter()
+                .map(|problem| format!("  {}", problem.describe()))
+                .collect::<Vec<String>>()
+                .join("\n")
+        )))
+    }
+
+    fn try_fix(&self) -> Result<bool, Box<BGitError>> {
+        // Choosing new names is up to the user
+        Ok(false)
+    }
+}
+
+impl PathSafetyRule {
+    fn open_repo(&self) -> Result<Repository, Box<BGitError>> {
+        let cwd = env::current_dir().map_err(|e| self.rule_error("Failed to get cwd", e))?;
+        Repository::discover(cwd).map_err(|e| self.rule_error("Failed to open repository", e))
+    }
+
+    /// Paths the staged change adds, including rename and copy targets
+    fn new_paths(repo: &Repository) -> Result<BTreeSet<String>, git2::Error> {
+        let head_tree = match repo.head() {
+            Ok(head) => Some(head.peel_to_tree()?),
+            Err(_) => None,
+        };
+        let index = repo.index()?;
+        let mut diff = repo.diff_tree_to_index(head_tree.as_ref(), Some(&index), None)?;
+        diff.find_similar(None)?;
+        Ok(diff
+            .deltas()
+            .filter(|delta| {
+                matches!(
+                    delta.status(),
+                    Delta::Added | Delta::Renamed | Delta::Copied
+                )
+            })
+            .filter_map(|delta| delta.new_file().path())
+            .map(|path| path.to_string_lossy().to_string())
+            .collect())
+    }
+
+    /// Case collision groups first, then problems of single paths in index order.
+    /// All of `paths` can collide, but only problems involving `new_paths` are reported,
+    /// so paths committed before don't block every later commit
+    fn find_problems(paths: &[String], new_paths: &BTreeSet<String>) -> Vec<PathProblem> {
+        let mut problems = Vec::new();
+        let new_prefixes: BTreeSet<String> = new_paths
+            .iter()
+            .flat_map(|path| {
+                let components: Vec<&str> = path.split('/').collect();
+                (1..=components.len())
+                    .map(|depth| components[..depth].join("/"))
+                    .collect::<Vec<String>>()
+            })
+            .collect();
+
+        // Directories collide as well, `Docs/a.md` and `docs/b.md` end up in one folder
+        let mut by_lowercase: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for path in paths {
+            let components: Vec<&str> = path.split('/').collect();
+            for depth in 1..=components.len() {
+                let prefix = components[..depth].join("/");
+                let group = by_lowercase.entry(prefix.to_lowercase()).or_default();
+                if !group.contains(&prefix) {
+                    group.push(prefix);
+                }
+            }
+        }
+        problems.extend(
+            by_lowercase
+                .into_values()
+                .filter(|group| {
+                    group.len() > 1 && group.iter().any(|prefix| new_prefixes.contains(prefix))
+                })
+                .map(PathProblem::CaseCollision),
+        );
+
+        for path in paths.iter().filter(|path| new_paths.contains(*path)) {
+            let length = path.chars().count();
+            if length > MAX_PATH_LENGTH {
+                problems.push(PathProblem::TooLong(path.clone(), length));
+            }
+            for component in path.split('/') {
+                if let Some(character) = component
+                    .chars()
+                    .find(|c| NTFS_ILLEGAL_CHARACTERS.contains(c) || c.is_control())
+                {
+                    problems.push(PathProblem::IllegalCharacter(path.clone(), character));
+                    break;
+                }
+                if component.ends_with('.') || component.ends_with(' ') {
+                    problems.push(PathProblem::TrailingDotOrSpace(path.clone()));
+                    break;
+                }
+                // `aux.txt` and `aux.tar.gz` are reserved too
+                let stem = component.split('.').next().unwrap_or_default().trim_end();
+                if WINDOWS_RESERVED_NAMES
+                    .iter()
+                    .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+                {
+                    problems.push(PathProblem::ReservedName(path.clone()));
+                    break;
+                }
+            }
+        }
+
+        problems
+    }
+
+    fn rule_error(&self, name: &str, error: impl std::fmt::Display) -> Box<BGitError> {
+        Box::new(BGitError::new(
+            name,
+            &error.to_string(),
+            BGitErrorWorkflowType::Rules,
+            NO_STEP,
+            NO_EVENT,
+            self.get_name(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::commit_index;
+    use std::fs;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_find_problems() {
+        let paths: Vec<String> = [
+            "README.md",
+            "Readme.md",
+            "Docs/a.md",
+            "docs/b.md",
+            "src/aux.rs",
+            "src/auxiliary.rs",
+            "notes./todo.txt",
+            "what?.txt",
+            "con",
+            "ok/file.txt",
+        ]
+        .iter()
+        .map(|path| path.to_string())
+        .collect();
+
+        let all: BTreeSet<String> = paths.iter().cloned().collect();
+        let problems = PathSafetyRule::find_problems(&paths, &all);
+        assert_eq!(
+            problems,
+            vec![
+                PathProblem::CaseCollision(vec![String::from("Docs"), String::from("docs")]),
+                PathProblem::CaseCollision(vec![
+                    String::from("README.md"),
+                    String::from("Readme.md")
+                ]),
+                PathProblem::ReservedName(String::from("src/aux.rs")),
+                PathProblem::TrailingDotOrSpace(String::from("notes./todo.txt")),
+                PathProblem::IllegalCharacter(String::from("what?.txt"), '?'),
+                PathProblem::ReservedName(String::from("con")),
+            ]
+        );
+
+        let long_path = format!("{}/file.txt", "a".repeat(MAX_PATH_LENGTH));
+        assert_eq!(
+            PathSafetyRule::find_problems(
+                std::slice::from_ref(&long_path),
+                &BTreeSet::from([long_path.clone()])
+            ),
+            vec![PathProblem::TooLong(long_path, MAX_PATH_LENGTH + 9)]
+        );
+    }
+
+    #[test]
+    fn test_only_new_paths_reported() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        let mut index = repo.index().unwrap();
+        for path in ["README.md", "con", "old.txt"] {
+            fs::write(temp_dir.path().join(path), path).unwrap();
+            index.add_path(Path::new(path)).unwrap();
+        }
+        index.write().unwrap();
+        commit_index(&repo, "init");
+
+        fs::rename(
+            temp_dir.path().join("old.txt"),
+            temp_dir.path().join("new?.txt"),
+        )
+        .unwrap();
+        fs::write(temp_dir.path().join("Readme.md"), "readme").unwrap();
+        index.remove_path(Path::new("old.txt")).unwrap();
+        index.add_path(Path::new("new?.txt")).unwrap();
+        index.add_path(Path::new("Readme.md")).unwrap();
+        index.write().unwrap();
+
+        let new_paths = PathSafetyRule::new_paths(&repo).unwrap();
+        assert_eq!(
+            new_paths,
+            BTreeSet::from([String::from("Readme.md"), String::from("new?.txt")])
+        );
+        let paths: Vec<String> = ["README.md", "Readme.md", "con", "new?.txt"]
+            .iter()
+            .map(|path| path.to_string())
+            .collect();
+        // The committed `con` is left alone, `README.md` is reported through the collision
+        assert_eq!(
+            PathSafetyRule::find_problems(&paths, &new_paths),
+            vec![
+                PathProblem::CaseCollision(vec![
+                    String::from("README.md"),
+                    String::from("Readme.md")
+                ]),
+                PathProblem::IllegalCharacter(String::from("new?.txt"), '?'),
+            ]
+        );
+    }
+}
@@ -0,0 +1,257 @@
+use crate::bgit_error::{BGitError, BGitErrorWorkflowType, NO_EVENT, NO_STEP};
+use crate::rules::{Rule, RuleLevel, RuleOutput};
+use git2::{Oid, Repository, SubmoduleIgnore, SubmoduleStatus};
+use std::env;
+
+#[derive(Debug, PartialEq)]
+enum SubmoduleProblem {
+    /// Only for a gitlink changed in the staged diff, others are `NotChecked`
+    Uninitialized,
+    /// Not initialized either, but the gitlink is unchanged, so it is only a warning
+    NotChecked,
+    Dirty,
+    Untracked,
+    /// The recorded commit isn't in the submodule's object database
+    MissingCommit(Oid),
+    /// No remote-tracking ref contains the recorded commit, so nobody else can fetch it
+    Unpushed(Oid),
+}
+
+impl SubmoduleProblem {
+    fn is_warning(&self) -> bool {
+        matches!(self, SubmoduleProblem::NotChecked)
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            SubmoduleProblem::Uninitialized => {
+                String::from("not initialized, so the staged commit can't be verified, run: git submodule update --init")
+            }
+            SubmoduleProblem::NotChecked => String::from(
+                "not initialized and was not checked, run: git submodule update --init",
+            ),
+            SubmoduleProblem::Dirty => String::from("has uncommitted changes"),
+            SubmoduleProblem::Untracked => String::from("has untracked files"),
+            SubmoduleProblem::MissingCommit(oid) => {
+                format!("records commit {} which the submodule doesn't have", oid)
+            }
+            SubmoduleProblem::Unpushed(oid) => format!(
+                "records commit {} which no remote-tracking branch of the submodule contains, push it first",
+                oid
+            ),
+        }
+    }
+}
+
+pub(crate) struct SubmoduleRule {
+    name: String,
+    description: String,
+    level: RuleLevel,
+}
+
+impl Rule for SubmoduleRule {
+    fn new() -> Self {
+        SubmoduleRule {
+            name: String::from("RULE_submodule-consistency"),
+            description: String::from(
+                "Submodules must be initialized, clean and point at pushed commits",
+            ),
+            level: RuleLevel::Error,
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_description(&self) -> &str {
+        &self.description
+    }
+
+    fn get_level(&self) -> RuleLevel {
+        self.level.clone()
+    }
+
+    fn check(&self) -> Result<RuleOutput, Box<BGitError>> {
+        let repo = self.open_repo()?;
+        let (warnings, problems): (Vec<_>, Vec<_>) = self
+            .find_problems(&repo)
+            .map_err(|e| self.rule_error("Failed to inspect submodules", e))?
+            .into_iter()
+            .partition(|(_, problem)| problem.is_warning());
+        for (path, warning) in &warnings {
+            eprintln!("Warning: submodule {} is {}", path, warning.describe());
+        }
+        if problems.is_empty() {
+            return Ok(RuleOutput::Success);
+        }
+        Ok(RuleOutput::Exception(format!(
+            "Submodules are not consistent:\n{}",
+            problems
+                .iter()
+                .map(|(path, problem)| format!("  {}: {}", path, problem.describe()))
+                .collect::<Vec<String>>()
+                .join("\n")
+        )))
+    }
+
+    fn try_fix(&self) -> Result<bool, Box<BGitError>> {
+        // Committing or pushing inside the submodule is up to the user
+        Ok(false)
+    }
+}
+
+impl SubmoduleRule {
+    fn open_repo(&self) -> Result<Repository, Box<BGitError>> {
+        let cwd = env::current_dir().map_err(|e| self.rule_error("Failed to get cwd", e))?;
+        Repository::discover(cwd).map_err(|e| self.rule_error("Failed to open repository", e))
+    }
+
+    fn find_problems(
+        &self,
+        repo: &Repository,
+    ) -> Result<Vec<(String, SubmoduleProblem)>, git2::Error> {
+        let mut problems = Vec::new();
+        for submodule in repo.submodules()? {
+            let path = submodule.path().to_string_lossy().to_string();
+            let name = match submodule.name() {
+                Some(name) => name.to_owned(),
+                None => continue,
+            };
+            let status = repo.submodule_status(&name, SubmoduleIgnore::None)?;
+            let sub_repo = match submodule.open() {
+                Ok(sub_repo) if !status.contains(SubmoduleStatus::WD_UNINITIALIZED) => sub_repo,
+                // Checkouts may leave optional submodules out, that only matters
+                // when the commit changes what they point to
+                _ if submodule.index_id() != submodule.head_id() => {
+                    problems.push((path, SubmoduleProblem::Uninitialized));
+                    continue;
+                }
+                _ => {
+                    problems.push((path, SubmoduleProblem::NotChecked));
+                    continue;
+                }
+            };
+
+            if status
+                .intersects(SubmoduleStatus::WD_INDEX_MODIFIED | SubmoduleStatus::WD_WD_MODIFIED)
+            {
+                problems.push((path.clone(), SubmoduleProblem::Dirty));
+            }
+            if status.contains(SubmoduleStatus::WD_UNTRACKED) {
+                problems.push((path.clone(), SubmoduleProblem::Untracked));
+            }
+
+            // The staged gitlink is what the next commit records, HEAD's is what a push sends
+            let mut recorded: Vec<Oid> = [submodule.index_id(), submodule.head_id()]
+                .into_iter()
+                .flatten()
+                .collect();
+            recorded.dedup();
+            for commit in recorded {
+                if sub_repo.find_commit(commit).is_err() {
+                    problems.push((path.clone(), SubmoduleProblem::MissingCommit(commit)));
+                } else if !Self::on_remote_tracking_ref(&sub_repo, commit)? {
+                    problems.push((path.clone(), SubmoduleProblem::Unpushed(commit)));
+                }
+            }
+        }
+        Ok(problems)
+    }
+
+    /// Whether a `refs/remotes/*` ref points at or descends from `commit`, no fetching
+    fn on_remote_tracking_ref(repo: &Repository, commit: Oid) -> Result<bool, git2::Error> {
+        for reference in repo.references_glob("refs/remotes/*")? {
+            let target = match reference?.resolve().ok().and_then(|r| r.target()) {
+                Some(target) => target,
+                None => continue,
+            };
+            if target == commit || repo.graph_descendant_of(target, commit)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn rule_error(&self, name: &str, error: impl std::fmt::Display) -> Box<BGitError> {
+        Box::new(BGitError::new(
+            name,
+            &error.to_string(),
+            BGitErrorWorkflowType::Rules,
+            NO_STEP,
+            NO_EVENT,
+            self.get_name(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::commit_index;
+    use git2::{IndexEntry, IndexTime};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_on_remote_tracking_ref() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        let first = commit_index(&repo, "First");
+        let second = commit_index(&repo, "Second");
+        assert!(!SubmoduleRule::on_remote_tracking_ref(&repo, first).unwrap());
+
+        repo.reference("refs/remotes/origin/main", first, false, "test")
+            .unwrap();
+        assert!(SubmoduleRule::on_remote_tracking_ref(&repo, first).unwrap());
+        assert!(!SubmoduleRule::on_remote_tracking_ref(&repo, second).unwrap());
+
+        repo.reference("refs/remotes/origin/main", second, true, "test")
+            .unwrap();
+        assert!(SubmoduleRule::on_remote_tracking_ref(&repo, first).unwrap());
+    }
+
+    #[test]
+    fn test_uninitialized_submodule() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        std::fs::write(
+            temp_dir.path().join(".gitmodules"),
+            "[submodule \"lib\"]\n\tpath = lib\n\turl = https://example.com/lib.git\n",
+        )
+        .unwrap();
+        // A gitlink in the index without a cloned submodule, as after a plain clone
+        let mut index = repo.index().unwrap();
+        index
+            .add(&IndexEntry {
+                ctime: IndexTime::new(0, 0),
+                mtime: IndexTime::new(0, 0),
+                dev: 0,
+                ino: 0,
+                mode: 0o160000,
+                uid: 0,
+                gid: 0,
+                file_size: 0,
+                id: Oid::from_str("1111111111111111111111111111111111111111").unwrap(),
+                flags: 0,
+                flags_extended: 0,
+                path: b"lib".to_vec(),
+            })
+            .unwrap();
+        index.write().unwrap();
+
+        let rule = SubmoduleRule::new();
+        assert_eq!(
+            rule.find_problems(&repo).unwrap(),
+            vec![(String::from("lib"), SubmoduleProblem::Uninitialized)]
+        );
+
+        // Once the gitlink is committed, an uninitialized submodule only warns
+        commit_index(&repo, "Add lib");
+        let problems = rule.find_problems(&repo).unwrap();
+        assert_eq!(
+            problems,
+            vec![(String::from("lib"), SubmoduleProblem::NotChecked)]
+        );
+        assert!(problems[0].1.is_warning());
+    }
+}
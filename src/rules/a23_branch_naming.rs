@@ -0,0 +1,413 @@
+use crate::bgit_config::BGitConfig;
+use crate::bgit_error::{BGitError, BGitErrorWorkflowType, NO_EVENT, NO_STEP};
+use crate::rules::a16_protected_branch::ProtectedBranchRule;
+use crate::rules::{Rule, RuleLevel, RuleOutput};
+use dialoguer::{theme::ColorfulTheme, Confirm, Input};
+use git2::{Branch, BranchType, Repository};
+use regex::Regex;
+use std::env;
+use std::sync::Mutex;
+
+/// Used when `.bgit/config` sets neither `branch-naming.prefix` nor `branch-naming.pattern`,
+/// violations of these only warn
+const DEFAULT_PREFIXES: [&str; 4] = ["feat/", "fix/", "chore/", "<ticket>-"];
+
+/// What `<ticket>` stands for in a prefix, e.g. `PROJ-123`
+const TICKET_PATTERN: &str = "[A-Za-z][A-Za-z0-9]*-[0-9]+";
+
+/// Allowed branch names, a name passes when any of the regexes matches it
+pub(crate) struct BranchPolicy {
+    allowed: Vec<Regex>,
+    /// The configured prefixes and patterns, for messages
+    rules: Vec<String>,
+    /// False when the policy is made of `DEFAULT_PREFIXES`
+    configured: bool,
+}
+
+impl BranchPolicy {
+    /// Reads `branch-naming.prefix` and `branch-naming.pattern` (both may be repeated)
+    pub(crate) fn load(repo: &Repository) -> Result<Self, regex::Error> {
+        let config = BGitConfig::load(repo);
+        let mut prefixes = config.get_all("branch-naming.prefix");
+        let patterns = config.get_all("branch-naming.pattern");
+        let configured = !prefixes.is_empty() || !patterns.is_empty();
+        if !configured {
+            prefixes = DEFAULT_PREFIXES.iter().map(|p| p.to_string()).collect();
+        }
+        Ok(BranchPolicy {
+            configured,
+            ..Self::new(&prefixes, &patterns)?
+        })
+    }
+
+    fn new(prefixes: &[String], patterns: &[String]) -> Result<Self, regex::Error> {
+        let mut allowed = Vec::new();
+        for prefix in prefixes {
+            // Something has to follow the prefix, `feat/` alone says nothing
+            let prefix = regex::escape(prefix).replace("<ticket>", TICKET_PATTERN);
+            allowed.push(Regex::new(&format!("^{}.+$", prefix))?);
+        }
+        for pattern in patterns {
+            allowed.push(Regex::new(pattern)?);
+        }
+        let rules = prefixes
+            .iter()
+            .map(|prefix| format!("{}*", prefix))
+            .chain(patterns.iter().map(|pattern| format!("/{}/", pattern)))
+            .collect();
+        Ok(BranchPolicy {
+            allowed,
+            rules,
+            configured: true,
+        })
+    }
+
+    pub(crate) fn is_configured(&self) -> bool {
+        self.configured
+    }
+
+    pub(crate) fn allows(&self, branch: &str) -> bool {
+        self.allowed.iter().any(|regex| regex.is_match(branch))
+    }
+
+    pub(crate) fn describe(&self) -> String {
+        self.rules.join(", ")
+    }
+
+    /// A conforming name derived from `branch`, keeping its words, `None` if no
+    /// candidate passes (e.g. only custom regexes are configured)
+    fn suggest(&self, branch: &str) -> Option<String> {
+        let slug = Self::slugify(branch);
+        let mut candidates = Vec::new();
+
+        // Keep the intent of common type words: feature-login -> feat/login
+        if let Some((kind, rest)) = slug.split_once(['/', '-']) {
+            let prefix = match kind.to_lowercase().as_str() {
+                "feat" | "feature" | "features" => Some("feat/"),
+                "fix" | "bug" | "bugfix" | "hotfix" => Some("fix/"),
+                "chore" | "maint" | "maintenance" => Some("chore/"),
+                _ => None,
+            };
+            if let Some(prefix) = prefix.filter(|_| !rest.is_empty()) {
+                candidates.push(format!("{}{}", prefix, rest));
+            }
+        }
+
+        // A ticket id anywhere in the name moves to the front: login-PROJ-12 -> PROJ-12-login
+        let ticket = Regex::new(&format!(r"\b{}\b", TICKET_PATTERN)).ok()?;
+        if let Some(found) = ticket.find(&slug) {
+            let rest = format!("{}{}", &slug[..found.start()], &slug[found.end()..]);
+            let rest = rest
+                .trim_matches(|c| c == '-' || c == '/')
+                .replace("--", "-");
+            if !rest.is_empty() {
+                candidates.push(format!("{}-{}", found.as_str(), rest));
+            }
+        }
+
+        for rule in &self.rules {
+            if let Some(prefix) = rule.strip_suffix('*').filter(|p| !p.contains("<ticket>")) {
+                candidates.push(format!("{}{}", prefix, slug));
+            }
+        }
+
+        candidates.into_iter().find(|candidate| {
+            self.allows(candidate) && Branch::name_is_valid(candidate).unwrap_or(false)
+        })
+    }
+
+    /// Replaces everything but letters, digits, `.`, `/` and `-` with `-`
+    fn slugify(branch: &str) -> String {
+        let mut slug = String::new();
+        for c in branch.trim().chars() {
+            let c = if c.is_ascii_alphanumeric() || c == '.' || c == '/' || c == '-' {
+                c
+            } else {
+                '-'
+            };
+            if !(c == '-' && slug.ends_with('-')) {
+                slug.push(c);
+            }
+        }
+        slug.trim_matches(|c| c == '-' || c == '/').to_owned()
+    }
+}
+
+pub(crate) struct BranchNamingRule {
+    name: String,
+    description: String,
+    level: RuleLevel,
+    /// Branch about to be created, `None` checks the checked out branch
+    new_branch: Mutex<Option<String>>,
+}
+
+impl Rule for BranchNamingRule {
+    fn new() -> Self {
+        BranchNamingRule {
+            name: String::from("RULE_branch-naming-policy"),
+            description: String::from(
+                "Branch names must start with a configured prefix or match a configured pattern",
+            ),
+            level: RuleLevel::Warning,
+            new_branch: Mutex::new(None),
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_description(&self) -> &str {
+        &self.description
+    }
+
+    /// Without a policy in `.bgit/config` the defaults only warn, even for new branches
+    fn get_level(&self) -> RuleLevel {
+        let configured = self
+            .open_repo()
+            .ok()
+            .and_then(|repo| BranchPolicy::load(&repo).ok())
+            .is_none_or(|policy| policy.is_configured());
+        if configured {
+            self.level.clone()
+        } else {
+            RuleLevel::Warning
+        }
+    }
+
+    fn check(&self) -> Result<RuleOutput, Box<BGitError>> {
+        let repo = self.open_repo()?;
+        let branch = match self.branch(&repo) {
+            Some(branch) => branch,
+            None => return Ok(RuleOutput::Success),
+        };
+        let policy = self.policy(&repo)?;
+        if Self::exempt(&repo, &branch) || policy.allows(&branch) {
+            return Ok(RuleOutput::Success);
+        }
+        Ok(RuleOutput::Exception(format!(
+            "Branch '{}' doesn't follow the naming policy, allowed: {}",
+            branch,
+            policy.describe()
+        )))
+    }
+
+    fn try_fix(&self) -> Result<bool, Box<BGitError>> {
+        let repo = self.open_repo()?;
+        let branch = match self.branch(&repo) {
+            Some(branch) => branch,
+            None => return Ok(true),
+        };
+        let policy = self.policy(&repo)?;
+        println!(
+            "Branch '{}' doesn't follow the naming policy, allowed: {}",
+            branch,
+            policy.describe()
+        );
+        if !policy.is_configured() {
+            println!(
+                "These are the default prefixes, set branch-naming.prefix or branch-naming.pattern \
+                in .bgit/config to enforce a policy"
+            );
+            return Ok(false);
+        }
+
+        let creating = self.new_branch.lock().unwrap().is_some();
+        let prompt = if creating {
+            "Create the branch under a conforming name instead?"
+        } else {
+            "Rename the branch to a conforming name?"
+        };
+        let confirmed = Confirm::new()
+            .with_prompt(prompt)
+            .default(true)
+            .interact()
+            .map_err(|e| self.rule_error("Input Error", e))?;
+        if !confirmed {
+            return Ok(false);
+        }
+
+        let theme = ColorfulTheme::default();
+        let mut input = Input::<String>::with_theme(&theme).with_prompt("New branch name");
+        if let Some(suggestion) = policy.suggest(&branch) {
+            input = input.default(suggestion);
+        }
+        let new_name = input
+            .validate_with(|input: &String| -> Result<(), String> {
+                let input = input.trim();
+                if !Branch::name_is_valid(input).unwrap_or(false) {
+                    return Err(format!("'{}' is not a valid branch name", input));
+                }
+                if !policy.allows(input) {
+                    return Err(format!("'{}' doesn't match {}", input, policy.describe()));
+                }
+                if repo.find_branch(input, BranchType::Local).is_ok() {
+                    return Err(format!("Branch '{}' already exists", input));
+                }
+                Ok(())
+            })
+            .interact_text()
+            .map_err(|e| self.rule_error("Input Error", e))?;
+        let new_name = new_name.trim().to_owned();
+
+        if creating {
+            *self.new_branch.lock().unwrap() = Some(new_name);
+            return Ok(true);
+        }
+        Self::rename(&repo, &branch, &new_name)
+            .map_err(|e| self.rule_error("Failed to rename branch", e))?;
+        println!("Renamed branch '{}' to '{}'", branch, new_name);
+        Ok(true)
+    }
+}
+
+impl BranchNamingRule {
+    /// Checks a branch name before `GitBranch` creates it, blocking on violations
+    pub(crate) fn for_new_branch(branch: &str) -> Self {
+        let rule = Self::new();
+        BranchNamingRule {
+            level: RuleLevel::Error,
+            new_branch: Mutex::new(Some(branch.to_owned())),
+            ..rule
+        }
+    }
+
+    /// Name to create the branch under, changed when `try_fix` picked another one
+    pub(crate) fn new_branch_name(&self) -> Option<String> {
+        self.new_branch.lock().unwrap().clone()
+    }
+
+    fn open_repo(&self) -> Result<Repository, Box<BGitError>> {
+        let cwd = env::current_dir().map_err(|e| self.rule_error("Failed to get cwd", e))?;
+        Repository::discover(cwd).map_err(|e| self.rule_error("Failed to open repository", e))
+    }
+
+    fn policy(&self, repo: &Repository) -> Result<BranchPolicy, Box<BGitError>> {
+        BranchPolicy::load(repo).map_err(|e| self.rule_error("Invalid branch-naming.pattern", e))
+    }
+
+    /// The branch being created, or the checked out one; `None` for a detached or unborn HEAD
+    fn branch(&self, repo: &Repository) -> Option<String> {
+        if let Some(branch) = self.new_branch.lock().unwrap().clone() {
+            return Some(branch);
+        }
+        let head = repo.head().ok()?;
+        if !head.is_branch() {
+            return None;
+        }
+        head.shorthand().map(String::from)
+    }
+
+    /// Long-lived branches are covered by `RULE_protect-main-branch` instead
+    fn exempt(repo: &Repository, branch: &str) -> bool {
+        ProtectedBranchRule::protected_pattern(repo, branch).is_some()
+    }
+
+    /// Renames a local branch, HEAD and the `branch.<name>.*` config follow it
+    fn rename(repo: &Repository, from: &str, to: &str) -> Result<(), git2::Error> {
+        let mut branch = repo.find_branch(from, BranchType::Local)?;
+        branch.rename(to, false)?;
+        Ok(())
+    }
+
+    fn rule_error(&self, name: &str, error: impl std::fmt::Display) -> Box<BGitError> {
+        Box::new(BGitError::new(
+            name,
+            &error.to_string(),
+            BGitErrorWorkflowType::Rules,
+            NO_STEP,
+            NO_EVENT,
+            self.get_name(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::commit_index;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn default_policy() -> BranchPolicy {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        BranchPolicy::load(&repo).unwrap()
+    }
+
+    #[test]
+    fn test_default_policy() {
+        let policy = default_policy();
+        assert!(!policy.is_configured());
+        assert!(policy.allows("feat/login-form"));
+        assert!(policy.allows("fix/typo"));
+        assert!(policy.allows("chore/bump-deps"));
+        assert!(policy.allows("PROJ-123-login"));
+        assert!(!policy.allows("feat/"));
+        assert!(!policy.allows("feature/login"));
+        assert!(!policy.allows("PROJ-login"));
+        assert!(!policy.allows("login"));
+    }
+
+    #[test]
+    fn test_configured_policy() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        fs::create_dir_all(temp_dir.path().join(".bgit")).unwrap();
+        fs::write(
+            temp_dir.path().join(".bgit/config"),
+            "[branch-naming]\n\tprefix = docs/\n\tpattern = ^deps-[0-9]+$\n",
+        )
+        .unwrap();
+        let policy = BranchPolicy::load(&repo).unwrap();
+        assert!(policy.is_configured());
+        assert!(policy.allows("docs/readme"));
+        assert!(policy.allows("deps-42"));
+        assert!(!policy.allows("feat/login"));
+        assert_eq!(policy.describe(), "docs/*, /^deps-[0-9]+$/");
+    }
+
+    #[test]
+    fn test_suggest() {
+        let policy = default_policy();
+        assert_eq!(
+            policy.suggest("feature-login"),
+            Some(String::from("feat/login"))
+        );
+        assert_eq!(
+            policy.suggest("Bugfix/null_check"),
+            Some(String::from("fix/null-check"))
+        );
+        assert_eq!(
+            policy.suggest("login-PROJ-12"),
+            Some(String::from("PROJ-12-login"))
+        );
+        assert_eq!(
+            policy.suggest("my new thing"),
+            Some(String::from("feat/my-new-thing"))
+        );
+
+        let custom = BranchPolicy::new(&[], &[String::from("^deps-[0-9]+$")]).unwrap();
+        assert_eq!(custom.suggest("login"), None);
+    }
+
+    #[test]
+    fn test_rename_moves_head() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        let commit = commit_index(&repo, "Initial");
+        repo.branch("login", &repo.find_commit(commit).unwrap(), false)
+            .unwrap();
+        repo.set_head("refs/heads/login").unwrap();
+
+        let rule = BranchNamingRule::new();
+        assert_eq!(rule.branch(&repo), Some(String::from("login")));
+        BranchNamingRule::rename(&repo, "login", "feat/login").unwrap();
+        assert_eq!(rule.branch(&repo), Some(String::from("feat/login")));
+        assert!(repo.find_branch("login", BranchType::Local).is_err());
+
+        let new_branch = BranchNamingRule::for_new_branch("fix/typo");
+        assert_eq!(new_branch.level, RuleLevel::Error);
+        assert_eq!(new_branch.branch(&repo), Some(String::from("fix/typo")));
+    }
+}
@@ -0,0 +1,339 @@
+use crate::bgit_error::{BGitError, BGitErrorWorkflowType, NO_EVENT, NO_STEP};
+use crate::rules::{Rule, RuleLevel, RuleOutput};
+use crate::util::program_exists;
+use dialoguer::Confirm;
+use git2::{BranchType, Oid, Repository, Sort};
+use std::env;
+use std::process::Command;
+
+#[derive(Debug, PartialEq)]
+enum CommitProblem {
+    Wip,
+    Temporary,
+    /// `fixup!`, `squash!` or `amend!`, meant to be folded in by `rebase --autosquash`
+    Autosquash(&'static str),
+    EmptyMessage,
+}
+
+impl CommitProblem {
+    fn classify(message: &str) -> Option<CommitProblem> {
+        let subject = message.trim_start().lines().next().unwrap_or("").trim_end();
+        if subject.is_empty() {
+            return Some(CommitProblem::EmptyMessage);
+        }
+        for marker in ["fixup!", "squash!", "amend!"] {
+            if subject.starts_with(marker) {
+                return Some(CommitProblem::Autosquash(marker));
+            }
+        }
+        if Self::starts_with_word(subject, "wip") {
+            return Some(CommitProblem::Wip);
+        }
+        if Self::starts_with_word(subject, "tmp") {
+            return Some(CommitProblem::Temporary);
+        }
+        None
+    }
+
+    /// Case insensitive, `WIP: x` and `tmp` match but `wipe cache` doesn't
+    fn starts_with_word(subject: &str, word: &str) -> bool {
+        subject
+            .get(..word.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(word))
+            && !subject[word.len()..]
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_alphanumeric())
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            CommitProblem::Wip => String::from("work in progress"),
+            CommitProblem::Temporary => String::from("temporary commit"),
+            CommitProblem::Autosquash(marker) => format!("{} commit not squashed yet", marker),
+            CommitProblem::EmptyMessage => String::from("empty commit message"),
+        }
+    }
+}
+
+struct OutgoingCommit {
+    id: Oid,
+    subject: String,
+    problem: CommitProblem,
+}
+
+pub(crate) struct OutgoingCommitsRule {
+    name: String,
+    description: String,
+    level: RuleLevel,
+}
+
+impl Rule for OutgoingCommitsRule {
+    fn new() -> Self {
+        OutgoingCommitsRule {
+            name: String::from("RULE_outgoing-commit-hygiene"),
+            description: String::from(
+                "Don't push WIP, tmp, fixup!, squash!, amend! or empty message commits",
+            ),
+            level: RuleLevel::Error,
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_description(&self) -> &str {
+        &self.description
+    }
+
+    fn get_level(&self) -> RuleLevel {
+        self.level.clone()
+    }
+
+    fn check(&self) -> Result<RuleOutput, Box<BGitError>> {
+        let repo = self.open_repo()?;
+        let flagged = self
+            .flagged_commits(&repo)
+            .map_err(|e| self.rule_error("Failed to walk outgoing commits", e))?;
+        if flagged.is_empty() {
+            return Ok(RuleOutput::Success);
+        }
+        Ok(RuleOutput::Exception(Self::format_flagged(&flagged)))
+    }
+
+    fn try_fix(&self) -> Result<bool, Box<BGitError>> {
+        let repo = self.open_repo()?;
+        let flagged = self
+            .flagged_commits(&repo)
+            .map_err(|e| self.rule_error("Failed to walk outgoing commits", e))?;
+        println!("{}", Self::format_flagged(&flagged));
+
+        let squashable = flagged
+            .iter()
+            .any(|commit| matches!(commit.problem, CommitProblem::Autosquash(_)));
+        if !squashable {
+            println!("Reword or squash these commits with: git rebase -i @{{upstream}}");
+            return Ok(false);
+        }
+
+        let confirmed = Confirm::new()
+            .with_prompt("Fold the fixup!/squash!/amend! commits in with an autosquash rebase?")
+            .default(true)
+            .interact()
+            .map_err(|e| self.rule_error("Input Error", e))?;
+        if !confirmed {
+            return Ok(false);
+        }
+
+        let base = self
+            .rebase_base(&repo)
+            .map_err(|e| self.rule_error("Failed to find the outgoing range", e))?;
+        if !program_exists("git") {
+            eprintln!("git is needed for the interactive rebase, install it and run bgit again");
+            return Ok(false);
+        }
+        let mut command = Command::new("git");
+        command.args(["rebase", "--interactive", "--autosquash", "--autostash"]);
+        match base {
+            Some(base) => command.arg(base.to_string()),
+            None => command.arg("--root"),
+        };
+        // Accept the todo list autosquash generated without opening an editor
+        let status = command
+            .env("GIT_SEQUENCE_EDITOR", "true")
+            .status()
+            .map_err(|e| self.rule_error("Failed to run git rebase", e))?;
+        if !status.success() {
+            println!("The rebase stopped, resolve it and continue with: git rebase --continue");
+            return Ok(false);
+        }
+        Ok(true)
+    }
+}
+
+impl OutgoingCommitsRule {
+    fn open_repo(&self) -> Result<Repository, Box<BGitError>> {
+        let cwd = env::current_dir().map_err(|e| self.rule_error("Failed to get cwd", e))?;
+        Repository::discover(cwd).map_err(|e| self.rule_error("Failed to open repository", e))
+    }
+
+    /// Upstream of the checked out branch, `None` when it has none or HEAD is detached
+    fn upstream(repo: &Repository) -> Option<Oid> {
+        let head = repo.head().ok()?;
+        let branch = repo
+            .find_branch(head.shorthand()?, BranchType::Local)
+            .ok()?;
+        branch.upstream().ok()?.get().target()
+    }
+
+    /// Commits a push would send, oldest first. Without an upstream, everything
+    /// not on any remote-tracking branch counts as outgoing
+    fn outgoing(repo: &Repository) -> Result<Vec<Oid>, git2::Error> {
+        if repo.head().is_err() {
+            return Ok(Vec::new());
+        }
+        let mut revwalk = repo.revwalk()?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+        revwalk.push_head()?;
+        match Self::upstream(repo) {
+            Some(upstream) => revwalk.hide(upstream)?,
+            None => revwalk.hide_glob("refs/remotes/*")?,
+        }
+        revwalk.collect()
+    }
+
+    fn flagged_commits(&self, repo: &Repository) -> Result<Vec<OutgoingCommit>, git2::Error> {
+        let mut flagged = Vec::new();
+        for id in Self::outgoing(repo)? {
+            let commit = repo.find_commit(id)?;
+            let message = String::from_utf8_lossy(commit.message_bytes()).to_string();
+            if let Some(problem) = CommitProblem::classify(&message) {
+                flagged.push(OutgoingCommit {
+                    id,
+                    subject: message.trim().lines().next().unwrap_or("").to_owned(),
+                    problem,
+                });
+            }
+        }
+        Ok(flagged)
+    }
+
+    /// Commit to rebase onto: the merge base with the upstream, so a diverged branch
+    /// isn't rebased onto incoming commits, else the parent of the oldest outgoing
+    /// commit; `None` when the range starts at a root commit
+    fn rebase_base(&self, repo: &Repository) -> Result<Option<Oid>, git2::Error> {
+        if let Some(upstream) = Self::upstream(repo) {
+            let head = repo.head()?.peel_to_commit()?.id();
+            return Ok(Some(repo.merge_base(head, upstream)?));
+        }
+        match Self::outgoing(repo)?.first() {
+            Some(oldest) => Ok(repo.find_commit(*oldest)?.parent_ids().next()),
+            None => Ok(None),
+        }
+    }
+
+    fn format_flagged(flagged: &[OutgoingCommit]) -> String {
+        format!(
+            "Outgoing commits aren't ready to push:\n{}",
+            flagged
+                .iter()
+                .map(|commit| {
+                    let id = commit.id.to_string();
+                    format!(
+                        "  {} {} ({})",
+                        &id[..7],
+                        commit.subject,
+                        commit.problem.describe()
+                    )
+                })
+                .collect::<Vec<String>>()
+                .join("\n")
+        )
+    }
+
+    fn rule_error(&self, name: &str, error: impl std::fmt::Display) -> Box<BGitError> {
+        Box::new(BGitError::new(
+            name,
+            &error.to_string(),
+            BGitErrorWorkflowType::Rules,
+            NO_STEP,
+            NO_EVENT,
+            self.get_name(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::commit_index;
+    use git2::Signature;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(CommitProblem::classify("WIP"), Some(CommitProblem::Wip));
+        assert_eq!(
+            CommitProblem::classify("wip: login form"),
+            Some(CommitProblem::Wip)
+        );
+        assert_eq!(
+            CommitProblem::classify("tmp"),
+            Some(CommitProblem::Temporary)
+        );
+        assert_eq!(
+            CommitProblem::classify("fixup! feat: add login"),
+            Some(CommitProblem::Autosquash("fixup!"))
+        );
+        assert_eq!(
+            CommitProblem::classify("amend! feat: add login\n\nfeat: add login form"),
+            Some(CommitProblem::Autosquash("amend!"))
+        );
+        assert_eq!(
+            CommitProblem::classify("  \n\n"),
+            Some(CommitProblem::EmptyMessage)
+        );
+        assert_eq!(CommitProblem::classify("wipe the cache on logout"), None);
+        assert_eq!(CommitProblem::classify("tmpfs support"), None);
+        assert_eq!(CommitProblem::classify("feat: add login"), None);
+    }
+
+    #[test]
+    fn test_outgoing_hides_upstream() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        let pushed = commit_index(&repo, "WIP already pushed");
+        let first = commit_index(&repo, "feat: add login");
+        let second = commit_index(&repo, "fixup! feat: add login");
+
+        // No upstream, every commit is outgoing
+        assert_eq!(
+            OutgoingCommitsRule::outgoing(&repo).unwrap(),
+            vec![pushed, first, second]
+        );
+
+        repo.reference("refs/remotes/origin/main", pushed, false, "test")
+            .unwrap();
+        assert_eq!(
+            OutgoingCommitsRule::outgoing(&repo).unwrap(),
+            vec![first, second]
+        );
+
+        repo.remote("origin", "https://example.com/repo.git")
+            .unwrap();
+        let branch = repo.head().unwrap().shorthand().unwrap().to_owned();
+        let mut config = repo.config().unwrap();
+        config
+            .set_str(&format!("branch.{}.remote", branch), "origin")
+            .unwrap();
+        config
+            .set_str(&format!("branch.{}.merge", branch), "refs/heads/main")
+            .unwrap();
+        assert_eq!(OutgoingCommitsRule::upstream(&repo), Some(pushed));
+
+        let rule = OutgoingCommitsRule::new();
+        let flagged = rule.flagged_commits(&repo).unwrap();
+        assert_eq!(flagged.len(), 1);
+        assert_eq!(flagged[0].id, second);
+        assert_eq!(rule.rebase_base(&repo).unwrap(), Some(pushed));
+
+        // After the upstream moved on, the rebase still only covers the local commits
+        let signature = Signature::now("Test", "test@example.com").unwrap();
+        let pushed_commit = repo.find_commit(pushed).unwrap();
+        let upstream_only = repo
+            .commit(
+                None,
+                &signature,
+                &signature,
+                "feat: upstream work",
+                &pushed_commit.tree().unwrap(),
+                &[&pushed_commit],
+            )
+            .unwrap();
+        repo.reference("refs/remotes/origin/main", upstream_only, true, "test")
+            .unwrap();
+        assert_eq!(OutgoingCommitsRule::upstream(&repo), Some(upstream_only));
+        assert_eq!(rule.rebase_base(&repo).unwrap(), Some(pushed));
+    }
+}
@@ -0,0 +1,275 @@
+use crate::bgit_error::{BGitError, BGitErrorWorkflowType, NO_EVENT, NO_STEP};
+use crate::rules::{Rule, RuleLevel, RuleOutput};
+use git2::{BranchType, Oid, Repository, Sort};
+use std::collections::BTreeSet;
+use std::env;
+
+/// Where the checked out branch stands relative to its last fetched upstream
+struct UpstreamStatus {
+    branch: String,
+    upstream: String,
+    ahead: usize,
+    behind: usize,
+    /// Incoming commits touching staged paths, with the paths they share
+    overlapping: Vec<(Oid, String, Vec<String>)>,
+}
+
+impl UpstreamStatus {
+    fn describe(&self) -> String {
+        let mut message = if self.ahead > 0 {
+            format!(
+                "Branch '{}' has diverged from '{}': {} local and {} incoming commit(s).",
+                self.branch, self.upstream, self.ahead, self.behind
+            )
+        } else {
+            format!(
+                "Branch '{}' is {} commit(s) behind '{}'.",
+                self.branch, self.behind, self.upstream
+            )
+        };
+        if !self.overlapping.is_empty() {
+            message.push_str("\nIncoming commits touching your staged files, expect conflicts:");
+            for (id, subject, paths) in &self.overlapping {
+                let id = id.to_string();
+                message.push_str(&format!(
+                    "\n  {} {} ({})",
+                    &id[..7],
+                    subject,
+                    paths.join(", ")
+                ));
+            }
+        }
+        message
+    }
+}
+
+pub(crate) struct BehindUpstreamRule {
+    name: String,
+    description: String,
+    level: RuleLevel,
+}
+
+impl Rule for BehindUpstreamRule {
+    fn new() -> Self {
+        BehindUpstreamRule {
+            name: String::from("RULE_behind-upstream"),
+            description: String::from(
+                "Warn when the branch is behind or has diverged from its fetched upstream",
+            ),
+            level: RuleLevel::Warning,
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_description(&self) -> &str {
+        &self.description
+    }
+
+    fn get_level(&self) -> RuleLevel {
+        self.level.clone()
+    }
+
+    fn check(&self) -> Result<RuleOutput, Box<BGitError>> {
+        let repo = self.open_repo()?;
+        match self
+            .upstream_status(&repo)
+            .map_err(|e| self.rule_error("Failed to compare with upstream", e))?
+        {
+            Some(status) if status.behind > 0 => Ok(RuleOutput::Exception(status.describe())),
+            _ => Ok(RuleOutput::Success),
+        }
+    }
+
+    fn try_fix(&self) -> Result<bool, Box<BGitError>> {
+        let repo = self.open_repo()?;
+        if let Some(status) = self
+            .upstream_status(&repo)
+            .map_err(|e| self.rule_error("Failed to compare with upstream", e))?
+        {
+            println!("{}", status.describe());
+            println!("Integrate the incoming commits after committing with: git pull --rebase");
+        }
+        // Pulling is left to the user, this only warns
+        Ok(false)
+    }
+}
+
+impl BehindUpstreamRule {
+    fn open_repo(&self) -> Result<Repository, Box<BGitError>> {
+        let cwd = env::current_dir().map_err(|e| self.rule_error("Failed to get cwd", e))?;
+        Repository::discover(cwd).map_err(|e| self.rule_error("Failed to open repository", e))
+    }
+
+    /// Compares HEAD with the remote-tracking ref of its upstream as last fetched,
+    /// `None` for a detached or unborn HEAD or a branch without upstream
+    fn upstream_status(&self, repo: &Repository) -> Result<Option<UpstreamStatus>, git2::Error> {
+        let head = match repo.head() {
+            Ok(head) if head.is_branch() => head,
+            _ => return Ok(None),
+        };
+        let (branch, local) = match (head.shorthand(), head.target()) {
+            (Some(branch), Some(local)) => (branch.to_owned(), local),
+            _ => return Ok(None),
+        };
+        let upstream_branch = match repo.find_branch(&branch, BranchType::Local)?.upstream() {
+            Ok(upstream_branch) => upstream_branch,
+            Err(_) => return Ok(None),
+        };
+        let upstream = upstream_branch.name()?.unwrap_or("upstream").to_owned();
+        let remote = match upstream_branch.get().target() {
+            Some(remote) => remote,
+            None => return Ok(None),
+        };
+
+        let (ahead, behind) = repo.graph_ahead_behind(local, remote)?;
+        let overlapping = if behind > 0 {
+            Self::overlapping_incoming(repo, local, remote)?
+        } else {
+            Vec::new()
+        };
+        Ok(Some(UpstreamStatus {
+            branch,
+            upstream,
+            ahead,
+            behind,
+            overlapping,
+        }))
+    }
+
+    /// Incoming commits (on `remote` but not `local`) that change a staged path, oldest first
+    fn overlapping_incoming(
+        repo: &Repository,
+        local: Oid,
+        remote: Oid,
+    ) -> Result<Vec<(Oid, String, Vec<String>)>, git2::Error> {
+        let staged = Self::staged_paths(repo)?;
+        if staged.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut revwalk = repo.revwalk()?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+        revwalk.push(remote)?;
+        revwalk.hide(local)?;
+
+        let mut overlapping = Vec::new();
+        for id in revwalk {
+            let commit = repo.find_commit(id?)?;
+            let parent_tree = match commit.parents().next() {
+                Some(parent) => Some(parent.tree()?),
+                None => None,
+            };
+            let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+            let touched: Vec<String> = Self::diff_paths(&diff)
+                .intersection(&staged)
+                .cloned()
+                .collect();
+            if !touched.is_empty() {
+                overlapping.push((
+                    commit.id(),
+                    commit.summary().unwrap_or("").to_owned(),
+                    touched,
+                ));
+            }
+        }
+        Ok(overlapping)
+    }
+
+    fn staged_paths(repo: &Repository) -> Result<BTreeSet<String>, git2::Error> {
+        let head_tree = match repo.head() {
+            Ok(head) => Some(head.peel_to_tree()?),
+            Err(_) => None,
+        };
+        let index = repo.index()?;
+        let diff = repo.diff_tree_to_index(head_tree.as_ref(), Some(&index), None)?;
+        Ok(Self::diff_paths(&diff))
+    }
+
+    /// Old and new paths of every delta, so renames count on both sides
+    fn diff_paths(diff: &git2::Diff) -> BTreeSet<String> {
+        diff.deltas()
+            .flat_map(|delta| [delta.old_file().path(), delta.new_file().path()])
+            .flatten()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect()
+    }
+
+    fn rule_error(&self, name: &str, error: impl std::fmt::Display) -> Box<BGitError> {
+        Box::new(BGitError::new(
+            name,
+            &error.to_string(),
+            BGitErrorWorkflowType::Rules,
+            NO_STEP,
+            NO_EVENT,
+            self.get_name(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::commit_file;
+    use std::fs;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_behind_with_overlapping_commit() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        let base = commit_file(&repo, "a.txt", "a\n", "Add a");
+        commit_file(&repo, "b.txt", "b\n", "Add b");
+        let touches_a = commit_file(&repo, "a.txt", "a2\n", "Change a");
+        let remote = commit_file(&repo, "c.txt", "c\n", "Add c");
+
+        // The upstream got three more commits since the local branch was at `base`
+        repo.reference("refs/remotes/origin/main", remote, false, "test")
+            .unwrap();
+        let branch = repo.head().unwrap().shorthand().unwrap().to_owned();
+        repo.reset(
+            repo.find_commit(base).unwrap().as_object(),
+            git2::ResetType::Hard,
+            None,
+        )
+        .unwrap();
+        repo.remote("origin", "https://example.com/repo.git")
+            .unwrap();
+        let mut config = repo.config().unwrap();
+        config
+            .set_str(&format!("branch.{}.remote", branch), "origin")
+            .unwrap();
+        config
+            .set_str(&format!("branch.{}.merge", branch), "refs/heads/main")
+            .unwrap();
+
+        let rule = BehindUpstreamRule::new();
+        let status = rule.upstream_status(&repo).unwrap().unwrap();
+        assert_eq!((status.ahead, status.behind), (0, 3));
+        assert!(status.overlapping.is_empty());
+
+        fs::write(temp_dir.path().join("a.txt"), "local\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("a.txt")).unwrap();
+        index.write().unwrap();
+
+        let status = rule.upstream_status(&repo).unwrap().unwrap();
+        assert_eq!(status.overlapping.len(), 1);
+        assert_eq!(status.overlapping[0].0, touches_a);
+        assert_eq!(status.overlapping[0].2, vec![String::from("a.txt")]);
+    }
+
+    #[test]
+    fn test_no_upstream() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        let rule = BehindUpstreamRule::new();
+        assert!(rule.upstream_status(&repo).unwrap().is_none());
+
+        commit_file(&repo, "a.txt", "a\n", "Add a");
+        assert!(rule.upstream_status(&repo).unwrap().is_none());
+    }
+}
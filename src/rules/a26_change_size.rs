@@ -0,0 +1,276 @@
+use crate::bgit_config::BGitConfig;
+use crate::bgit_error::{BGitError, BGitErrorWorkflowType, NO_EVENT, NO_STEP};
+use crate::rules::{Rule, RuleLevel, RuleOutput};
+use crate::util::is_generated_file;
+use git2::{Diff, DiffOptions, Patch, Repository};
+use std::collections::BTreeMap;
+use std::env;
+use std::path::Path;
+
+const DEFAULT_MAX_FILES: usize = 50;
+const DEFAULT_MAX_LINES: usize = 1500;
+
+/// Size of the staged change with excluded paths left out
+struct ChangeSize {
+    files: usize,
+    insertions: usize,
+    deletions: usize,
+    /// `(files, insertions, deletions)` per directory, to suggest how to split
+    by_directory: BTreeMap<String, (usize, usize, usize)>,
+}
+
+impl ChangeSize {
+    fn lines(&self) -> usize {
+        self.insertions + self.deletions
+    }
+}
+
+pub(crate) struct ChangeSizeRule {
+    name: String,
+    description: String,
+    level: RuleLevel,
+}
+
+impl Rule for ChangeSizeRule {
+    fn new() -> Self {
+        ChangeSizeRule {
+            name: String::from("RULE_oversized-change"),
+            description: String::from(
+                "Warn when a commit changes more files or lines than reviewers can handle",
+            ),
+            level: RuleLevel::Warning,
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_description(&self) -> &str {
+        &self.description
+    }
+
+    fn get_level(&self) -> RuleLevel {
+        self.level.clone()
+    }
+
+    fn check(&self) -> Result<RuleOutput, Box<BGitError>> {
+        let repo = self.open_repo()?;
+        match self.oversized(&repo)? {
+            Some(message) => Ok(RuleOutput::Exception(message)),
+            None => Ok(RuleOutput::Success),
+        }
+    }
+
+    fn try_fix(&self) -> Result<bool, Box<BGitError>> {
+        let repo = self.open_repo()?;
+        if let Some(message) = self.oversized(&repo)? {
+            println!("{}", message);
+            println!(
+                "Unstage a directory with: git restore --staged <directory>, commit the rest, then stage it again"
+            );
+        }
+        // Splitting the commit is up to the user, this only warns
+        Ok(false)
+    }
+}
+
+impl ChangeSizeRule {
+    fn open_repo(&self) -> Result<Repository, Box<BGitError>> {
+        let cwd = env::current_dir().map_err(|e| self.rule_error("Failed to get cwd", e))?;
+        Repository::discover(cwd).map_err(|e| self.rule_error("Failed to open repository", e))
+    }
+
+    fn max_files(repo: &Repository) -> usize {
+        BGitConfig::load(repo)
+            .get_i64("change-size.maxFiles")
+            .and_then(|count| usize::try_from(count).ok())
+            .unwrap_or(DEFAULT_MAX_FILES)
+    }
+
+    fn max_lines(repo: &Repository) -> usize {
+        BGitConfig::load(repo)
+            .get_i64("change-size.maxLines")
+            .and_then(|count| usize::try_from(count).ok())
+            .unwrap_or(DEFAULT_MAX_LINES)
+    }
+
+    /// The warning when a budget is exceeded, `None` otherwise
+    fn oversized(&self, repo: &Repository) -> Result<Option<String>, Box<BGitError>> {
+        let size = self
+            .change_size(repo)
+            .map_err(|e| self.rule_error("Failed to measure staged changes", e))?;
+        let (max_files, max_lines) = (Self::max_files(repo), Self::max_lines(repo));
+        if size.files <= max_files && size.lines() <= max_lines {
+            return Ok(None);
+        }
+
+        let mut message = format!(
+            "The staged change is too large to review: {} files (budget {}), {} changed lines (budget {}), \
+            lockfiles and generated files not counted.\nConsider splitting it into one commit per directory:",
+            size.files,
+            max_files,
+            size.lines(),
+            max_lines
+        );
+        let mut directories: Vec<_> = size.by_directory.iter().collect();
+        directories.sort_by_key(|(_, (files, insertions, deletions))| {
+            std::cmp::Reverse((insertions + deletions, *files))
+        });
+        for (directory, (files, insertions, deletions)) in directories {
+            message.push_str(&format!(
+                "\n  {}: {} file(s), +{} -{}",
+                directory, files, insertions, deletions
+            ));
+        }
+        Ok(Some(message))
+    }
+
+    /// `Diff::stats` of HEAD's tree against the index, restricted to the paths
+    /// that aren't excluded
+    fn change_size(&self, repo: &Repository) -> Result<ChangeSize, git2::Error> {
+        let head_tree = match repo.head() {
+            Ok(head) => Some(head.peel_to_tree()?),
+            Err(_) => None,
+        };
+        let index = repo.index()?;
+        let diff = repo.diff_tree_to_index(head_tree.as_ref(), Some(&index), None)?;
+        let excludes = BGitConfig::load(repo).get_all("change-size.exclude");
+
+        let counted: Vec<String> = diff
+            .deltas()
+            .filter_map(|delta| delta.new_file().path().or(delta.old_file().path()))
+            .filter(|path| !is_generated_file(repo, &excludes, path))
+            .map(|path| path.to_string_lossy().to_string())
+            .collect();
+        let mut size = ChangeSize {
+            files: 0,
+            insertions: 0,
+            deletions: 0,
+            by_directory: BTreeMap::new(),
+        };
+        // An empty pathspec would match everything
+        if counted.is_empty() {
+            return Ok(size);
+        }
+
+        let mut options = DiffOptions::new();
+        options.disable_pathspec_match(true);
+        for path in &counted {
+            options.pathspec(path);
+        }
+        let diff = repo.diff_tree_to_index(head_tree.as_ref(), Some(&index), Some(&mut options))?;
+        let stats = diff.stats()?;
+        size.files = stats.files_changed();
+        size.insertions = stats.insertions();
+        size.deletions = stats.deletions();
+        size.by_directory = Self::by_directory(&diff)?;
+        Ok(size)
+    }
+
+    fn by_directory(diff: &Diff) -> Result<BTreeMap<String, (usize, usize, usize)>, git2::Error> {
+        let mut by_directory: BTreeMap<String, (usize, usize, usize)> = BTreeMap::new();
+        for (delta_index, delta) in diff.deltas().enumerate() {
+            let path = match delta.new_file().path().or(delta.old_file().path()) {
+                Some(path) => path,
+                None => continue,
+            };
+            let (insertions, deletions) = match Patch::from_diff(diff, delta_index)? {
+                Some(patch) => {
+                    let (_, insertions, deletions) = patch.line_stats()?;
+                    (insertions, deletions)
+                }
+                None => (0, 0),
+            };
+            let entry = by_directory.entry(Self::directory(path)).or_default();
+            entry.0 += 1;
+            entry.1 += insertions;
+            entry.2 += deletions;
+        }
+        Ok(by_directory)
+    }
+
+    /// Up to two leading directories, `src/rules/a.rs` -> `src/rules`, `README.md` -> `.`
+    fn directory(path: &Path) -> String {
+        let components: Vec<String> = path
+            .parent()
+            .map(|parent| {
+                parent
+                    .components()
+                    .take(2)
+                    .map(|c| c.as_os_str().to_string_lossy().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        if components.is_empty() {
+            String::from(".")
+        } else {
+            components.join("/")
+        }
+    }
+
+    fn rule_error(&self, name: &str, error: impl std::fmt::Display) -> Box<BGitError> {
+        Box::new(BGitError::new(
+            name,
+            &error.to_string(),
+            BGitErrorWorkflowType::Rules,
+            NO_STEP,
+            NO_EVENT,
+            self.get_name(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn stage(repo: &Repository, path: &str, content: &str) {
+        let full_path = repo.workdir().unwrap().join(path);
+        fs::create_dir_all(full_path.parent().unwrap()).unwrap();
+        fs::write(full_path, content).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(path)).unwrap();
+        index.write().unwrap();
+    }
+
+    #[test]
+    fn test_change_size_excludes_lockfiles_and_generated() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        stage(&repo, "src/rules/a.rs", "one\ntwo\n");
+        stage(&repo, "src/main.rs", "one\n");
+        stage(&repo, "README.md", "one\n");
+        stage(&repo, "Cargo.lock", &"lock\n".repeat(500));
+        stage(&repo, "web/dist/app.min.js", &"x\n".repeat(500));
+        stage(&repo, ".gitattributes", "schema.rs linguist-generated\n");
+        stage(&repo, "src/schema.rs", &"table\n".repeat(500));
+
+        let size = ChangeSizeRule::new().change_size(&repo).unwrap();
+        assert_eq!(size.files, 4);
+        assert_eq!((size.insertions, size.deletions), (5, 0));
+        assert_eq!(size.by_directory.get("src/rules"), Some(&(1, 2, 0)));
+        assert_eq!(size.by_directory.get("src"), Some(&(1, 1, 0)));
+        assert_eq!(size.by_directory.get("."), Some(&(2, 2, 0)));
+    }
+
+    #[test]
+    fn test_budgets() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        let rule = ChangeSizeRule::new();
+        stage(&repo, "src/big.rs", &"line\n".repeat(20));
+        assert!(rule.oversized(&repo).unwrap().is_none());
+
+        stage(
+            &repo,
+            ".bgit/config",
+            "[change-size]\n\tmaxLines = 10\n\texclude = .bgit/*\n",
+        );
+        let message = rule.oversized(&repo).unwrap().unwrap();
+        assert!(message.contains("20 changed lines (budget 10)"));
+        assert!(message.contains("src: 1 file(s), +20 -0"));
+    }
+}
@@ -0,0 +1,255 @@
+use crate::util::program_exists;
+use git2::Config;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tempfile::NamedTempFile;
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum SigningFormat {
+    OpenPgp,
+    Ssh,
+}
+
+/// Commit signing as configured through `commit.gpgSign`, `gpg.format` and `user.signingKey`.
+/// Signing is delegated to the same programs git itself uses (`gpg` and `ssh-keygen`).
+#[derive(Debug)]
+pub(crate) struct CommitSigner {
+    format: SigningFormat,
+    signing_key: Option<String>,
+    program: String,
+}
+
+impl CommitSigner {
+    /// `Ok(None)` when `commit.gpgSign` is not enabled
+    pub(crate) fn from_config(config: &Config) -> Result<Option<Self>, String> {
+        if !config.get_bool("commit.gpgSign").unwrap_or(false) {
+            return Ok(None);
+        }
+
+        let format = match config.get_string("gpg.format").as_deref() {
+            Err(_) | Ok("openpgp") => SigningFormat::OpenPgp,
+            Ok("ssh") => SigningFormat::Ssh,
+            Ok(other) => return Err(format!("Unsupported gpg.format '{}'", other)),
+        };
+
+        let program = match format {
+            SigningFormat::OpenPgp => config
+                .get_string("gpg.openpgp.program")
+                .or_else(|_| config.get_string("gpg.program"))
+                .unwrap_or_else(|_| String::from("gpg")),
+            SigningFormat::Ssh => config
+                .get_string("gpg.ssh.program")
+                .unwrap_or_else(|_| String::from("ssh-keygen")),
+        };
+
+        let signing_key = config
+            .get_string("user.signingKey")
+            .ok()
+            .filter(|key| !key.trim().is_empty());
+        if format == SigningFormat::Ssh && signing_key.is_none() {
+            return Err(String::from(
+                "gpg.format is ssh, but user.signingKey is not set",
+            ));
+        }
+
+        Ok(Some(CommitSigner {
+            format,
+            signing_key,
+            program,
+        }))
+    }
+
+    /// Cheap checks that signing can work, without asking for a passphrase
+    pub(crate) fn check_usable(&self) -> Result<(), String> {
+        if !program_exists(&self.program) {
+            return Err(format!("Signing program '{}' was not found", self.program));
+        }
+
+        match self.format {
+            SigningFormat::OpenPgp => {
+                let mut command = Command::new(&self.program);
+                command.arg("--list-secret-keys");
+                if let Some(key) = &self.signing_key {
+                    command.arg(key);
+                }
+                let output = command
+                    .output()
+                    .map_err(|e| format!("Failed to run {}: {}", self.program, e))?;
+                if output.status.success() && !output.stdout.is_empty() {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "No secret key found for '{}'",
+                        self.signing_key.as_deref().unwrap_or("the committer")
+                    ))
+                }
+            }
+            SigningFormat::Ssh => {
+                let key = self.signing_key.as_deref().unwrap_or_default();
+                if Self::is_literal_ssh_key(key) {
+                    if env::var_os("SSH_AUTH_SOCK").is_some() {
+                        Ok(())
+                    } else {
+                        Err(String::from(
+                            "user.signingKey is a public key, but no ssh-agent is running",
+                        ))
+                    }
+                } else if Self::expand_home(key).is_file() {
+                    Ok(())
+                } else {
+                    Err(format!("SSH signing key '{}' does not exist", key))
+                }
+            }
+        }
+    }
+
+    /// Detached, armored signature of the commit buffer
+    pub(crate) fn sign(&self, buffer: &str) -> Result<String, String> {
+        match self.format {
+            SigningFormat::OpenPgp => self.sign_openpgp(buffer),
+            SigningFormat::Ssh => self.sign_ssh(buffer),
+        }
+    }
+
+    fn sign_openpgp(&self, buffer: &str) -> Result<String, String> {
+        let mut command = Command::new(&self.program);
+        command.args(["--status-fd=2", "-bsa"]);
+        if let Some(key) = &self.signing_key {
+            command.args(["-u", key]);
+        }
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to run {}: {}", self.program, e))?;
+
+        child
+            .stdin
+            .take()
+            .ok_or_else(|| String::from("Failed to open stdin of the signing program"))?
+            .write_all(buffer.as_bytes())
+            .map_err(|e| e.to_string())?;
+
+        let output = child.wait_with_output().map_err(|e| e.to_string())?;
+        if !output.status.success() {
+            return Err(format!(
+                "{} failed to sign the commit: {}",
+                self.program,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    fn sign_ssh(&self, buffer: &str) -> Result<String, String> {
+        let key = self.signing_key.as_deref().unwrap_or_default();
+
+        // A literal public key means the private key lives in ssh-agent, like git does it
+        let literal_key_file;
+        let mut command = Command::new(&self.program);
+        command.args(["-Y", "sign", "-n", "git", "-f"]);
+        if Self::is_literal_ssh_key(key) {
+            literal_key_file = NamedTempFile::new().map_err(|e| e.to_string())?;
+            fs::write(literal_key_file.path(), key.trim_start_matches("key::"))
+                .map_err(|e| e.to_string())?;
+            command.arg(literal_key_file.path()).arg("-U");
+        } else {
+            command.arg(Self::expand_home(key));
+        }
+
+        let buffer_file = NamedTempFile::new().map_err(|e| e.to_string())?;
+        fs::write(buffer_file.path(), buffer).map_err(|e| e.to_string())?;
+        let output = command
+            .arg(buffer_file.path())
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|e| format!("Failed to run {}: {}", self.program, e))?;
+
+        let signature_path = PathBuf::from(format!("{}.sig", buffer_file.path().display()));
+        let signature = fs::read_to_string(&signature_path);
+        let _ = fs::remove_file(&signature_path);
+
+        if !output.status.success() {
+            return Err(format!(
+                "{} failed to sign the commit: {}",
+                self.program,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        signature.map_err(|e| format!("Failed to read SSH signature: {}", e))
+    }
+
+    fn is_literal_ssh_key(key: &str) -> bool {
+        key.starts_with("key::") || key.starts_with("ssh-") || key.starts_with("ecdsa-")
+    }
+
+    fn expand_home(path: &str) -> PathBuf {
+        match (path.strip_prefix("~/"), env::var_os("HOME")) {
+            (Some(rest), Some(home)) => Path::new(&home).join(rest),
+            _ => PathBuf::from(path),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn config_with(content: &str) -> (TempDir, Config) {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("gitconfig");
+        fs::write(&config_path, content).unwrap();
+        let config = Config::open(&config_path).unwrap();
+        (temp_dir, config)
+    }
+
+    #[test]
+    fn test_signer_from_config() {
+        let (_temp_dir, config) = config_with("[user]\n\tname = Test\n");
+        assert!(CommitSigner::from_config(&config).unwrap().is_none());
+
+        let (_temp_dir, config) =
+            config_with("[commit]\n\tgpgSign = true\n[user]\n\tsigningKey = ABCD1234\n");
+        let signer = CommitSigner::from_config(&config).unwrap().unwrap();
+        assert_eq!(signer.format, SigningFormat::OpenPgp);
+        assert_eq!(signer.program, "gpg");
+        assert_eq!(signer.signing_key.as_deref(), Some("ABCD1234"));
+
+        let (_temp_dir, config) =
+            config_with("[commit]\n\tgpgSign = true\n[gpg]\n\tformat = ssh\n");
+        assert!(CommitSigner::from_config(&config).is_err());
+
+        let (_temp_dir, config) =
+            config_with("[commit]\n\tgpgSign = true\n[gpg]\n\tformat = x509\n");
+        assert!(CommitSigner::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_ssh_signing() {
+        if !program_exists("ssh-keygen") {
+            return;
+        }
+        let temp_dir = TempDir::new().unwrap();
+        let key_path = temp_dir.path().join("id_ed25519");
+        let generated = Command::new("ssh-keygen")
+            .args(["-q", "-t", "ed25519", "-N", "", "-f"])
+            .arg(&key_path)
+            .status()
+            .unwrap();
+        assert!(generated.success());
+
+        let signer = CommitSigner {
+            format: SigningFormat::Ssh,
+            signing_key: Some(key_path.to_string_lossy().to_string()),
+            program: String::from("ssh-keygen"),
+        };
+        assert!(signer.check_usable().is_ok());
+        let signature = signer.sign("tree 0000\n\ncommit message\n").unwrap();
+        assert!(signature.starts_with("-----BEGIN SSH SIGNATURE-----"));
+    }
+}
@@ -0,0 +1,164 @@
+use crate::util::wildcard_match;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// One `Host` section of `~/.ssh/config`. Options before the first `Host` line
+/// apply to every host and end up in a `Host *` block.
+#[derive(Debug)]
+struct HostBlock {
+    patterns: Vec<String>,
+    options: Vec<(String, String)>,
+}
+
+/// Just enough of the OpenSSH client config to find users and identity files per host.
+/// `Match` and `Include` are not evaluated.
+#[derive(Debug)]
+pub(crate) struct SshConfig {
+    blocks: Vec<HostBlock>,
+}
+
+/// `~/.ssh`, or `%USERPROFILE%\.ssh` on Windows
+pub(crate) fn ssh_dir() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".ssh"))
+}
+
+impl SshConfig {
+    pub(crate) fn load() -> Option<Self> {
+        let content = fs::read_to_string(ssh_dir()?.join("config")).ok()?;
+        Some(Self::parse(&content))
+    }
+
+    pub(crate) fn parse(content: &str) -> Self {
+        let mut blocks = vec![HostBlock {
+            patterns: vec![String::from("*")],
+            options: Vec::new(),
+        }];
+        let mut in_match_block = false;
+
+        for line in content.lines() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let (keyword, value) = match trimmed.split_once(|c: char| c.is_whitespace() || c == '=')
+            {
+                Some((keyword, value)) => (
+                    keyword.to_lowercase(),
+                    value.trim_start_matches(|c: char| c.is_whitespace() || c == '='),
+                ),
+                None => continue,
+            };
+            let value = value.trim().trim_matches('"');
+
+            match keyword.as_str() {
+                "host" => {
+                    in_match_block = false;
+                    blocks.push(HostBlock {
+                        patterns: value.split_whitespace().map(String::from).collect(),
+                        options: Vec::new(),
+                    });
+                }
+                "match" => in_match_block = true,
+                _ if in_match_block => {}
+                _ => {
+                    if let Some(block) = blocks.last_mut() {
+                        block.options.push((keyword, value.to_owned()));
+                    }
+                }
+            }
+        }
+
+        SshConfig { blocks }
+    }
+
+    /// Values of `keyword` for `host`, in file order. A block applies when one of its
+    /// `Host` patterns matches, or when its `HostName` is `host` (an alias for it).
+    pub(crate) fn options_for(&self, host: &str, keyword: &str) -> Vec<&str> {
+        let keyword = keyword.to_lowercase();
+        self.blocks
+            .iter()
+            .filter(|block| block.matches(host))
+            .flat_map(|block| block.options.iter())
+            .filter(|(option, _)| *option == keyword)
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+
+    /// `HostName` configured for an alias, the alias itself otherwise
+    pub(crate) fn resolve_host(&self, alias: &str) -> String {
+        self.blocks
+            .iter()
+            .filter(|block| block.matches_pattern(alias))
+            .flat_map(|block| block.options.iter())
+            .find(|(option, _)| option == "hostname")
+            .map(|(_, hostname)| hostname.replace("%h", alias))
+            .unwrap_or_else(|| alias.to_owned())
+    }
+}
+
+impl HostBlock {
+    fn matches(&self, host: &str) -> bool {
+        self.matches_pattern(host)
+            || self
+                .options
+                .iter()
+                .any(|(option, value)| option == "hostname" && value.eq_ignore_ascii_case(host))
+    }
+
+    fn matches_pattern(&self, host: &str) -> bool {
+        let host = host.to_lowercase();
+        let mut matched = false;
+        for pattern in &self.patterns {
+            let pattern = pattern.to_lowercase();
+            match pattern.strip_prefix('!') {
+                Some(negated) if wildcard_match(negated, &host) => return false,
+                Some(_) => {}
+                None => matched |= wildcard_match(&pattern, &host),
+            }
+        }
+        matched
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_options_for_host() {
+        let config = SshConfig::parse(
+            "IdentitiesOnly yes\n\
+            Host github.com\n    User git\n    IdentityFile ~/.ssh/github\n\n\
+            Host work\n    User dave\n    HostName gitlab.example.com\n\n\
+            Host *.example.com !internal.example.com\n    IdentityFile=~/.ssh/example\n\n\
+            Match host foo\n    User ignored\n",
+        );
+
+        assert_eq!(
+            config.options_for("github.com", "IdentityFile"),
+            vec!["~/.ssh/github"]
+        );
+        assert_eq!(
+            config.options_for("github.com", "identitiesonly"),
+            vec!["yes"]
+        );
+        assert_eq!(
+            config.options_for("gitlab.example.com", "user"),
+            vec!["dave"]
+        );
+        assert_eq!(
+            config.options_for("gitlab.example.com", "identityfile"),
+            vec!["~/.ssh/example"]
+        );
+        assert!(config
+            .options_for("internal.example.com", "identityfile")
+            .is_empty());
+        assert!(config.options_for("foo", "user").is_empty());
+
+        assert_eq!(config.resolve_host("work"), "gitlab.example.com");
+        assert_eq!(config.resolve_host("github.com"), "github.com");
+    }
+}
@@ -0,0 +1,67 @@
+use git2::{Oid, Repository, Signature};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+/// The cwd is shared by all tests, which cargo runs in parallel. Every test that
+/// resolves the repository from the cwd goes through `enter_dir`
+static CWD_LOCK: Mutex<()> = Mutex::new(());
+
+/// Keeps the cwd at a test repository until dropped, then restores it
+pub(crate) struct CwdGuard {
+    previous: PathBuf,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl Drop for CwdGuard {
+    fn drop(&mut self) {
+        let _ = env::set_current_dir(&self.previous);
+    }
+}
+
+pub(crate) fn enter_dir(path: &Path) -> CwdGuard {
+    // A test that panicked while holding the lock has already restored the cwd
+    let lock = CWD_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let previous = env::current_dir().unwrap();
+    env::set_current_dir(path).unwrap();
+    CwdGuard {
+        previous,
+        _lock: lock,
+    }
+}
+
+/// Commits the index on top of HEAD, or as a root commit on an unborn branch
+pub(crate) fn commit_index(repo: &Repository, message: &str) -> Oid {
+    let signature = Signature::now("Test", "test@example.com").unwrap();
+    let tree = repo
+        .find_tree(repo.index().unwrap().write_tree().unwrap())
+        .unwrap();
+    let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+    let parents: Vec<&git2::Commit> = parent.iter().collect();
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &parents,
+    )
+    .unwrap()
+}
+
+/// Writes `path` in the working tree, stages it and commits the index
+pub(crate) fn commit_file(
+    repo: &Repository,
+    path: &str,
+    content: impl AsRef<[u8]>,
+    message: &str,
+) -> Oid {
+    let full_path = repo.workdir().unwrap().join(path);
+    fs::create_dir_all(full_path.parent().unwrap()).unwrap();
+    fs::write(full_path, content).unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new(path)).unwrap();
+    index.write().unwrap();
+    commit_index(repo, message)
+}
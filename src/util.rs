@@ -1,5 +1,119 @@
+use git2::{AttrCheckFlags, AttrValue, Diff, Patch, Repository};
+use std::env;
 use std::path::{Path, PathBuf};
 
+/// Whether `program` can be run, either as a path or found on `PATH`
+pub(crate) fn program_exists(program: &str) -> bool {
+    if Path::new(program).components().count() > 1 {
+        return Path::new(program).is_file();
+    }
+    let extensions: &[&str] = if cfg!(windows) { &["", ".exe"] } else { &[""] };
+    env::var_os("PATH").is_some_and(|paths| {
+        env::split_paths(&paths).any(|dir| {
+            extensions
+                .iter()
+                .any(|extension| dir.join(format!("{}{}", program, extension)).is_file())
+        })
+    })
+}
+
+/// Line number and content (without the line ending) of the lines `diff` adds to the
+/// file at `delta_index`
+pub(crate) fn added_lines(
+    diff: &Diff,
+    delta_index: usize,
+) -> Result<Vec<(usize, String)>, git2::Error> {
+    let mut lines = Vec::new();
+    if let Some(patch) = Patch::from_diff(diff, delta_index)? {
+        for hunk_index in 0..patch.num_hunks() {
+            for line_index in 0..patch.num_lines_in_hunk(hunk_index)? {
+                let line = patch.line_in_hunk(hunk_index, line_index)?;
+                if let ('+', Some(line_number)) = (line.origin(), line.new_lineno()) {
+                    let content = String::from_utf8_lossy(line.content());
+                    lines.push((line_number as usize, content.trim_end().to_owned()));
+                }
+            }
+        }
+    }
+    Ok(lines)
+}
+
+/// Lockfiles and generated sources, nobody reviews or writes them by hand
+const GENERATED_FILE_PATTERNS: [&str; 14] = [
+    "Cargo.lock",
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "poetry.lock",
+    "Pipfile.lock",
+    "Gemfile.lock",
+    "composer.lock",
+    "go.sum",
+    "*.min.js",
+    "*.min.css",
+    "*.pb.go",
+    "*_pb2.py",
+];
+
+/// Whether `path` is a lockfile, a generated source, matches one of `extra_patterns` or is
+/// marked `linguist-generated` in `.gitattributes`. Globs without `/` match the file name,
+/// others the whole path
+pub(crate) fn is_generated_file(repo: &Repository, extra_patterns: &[String], path: &Path) -> bool {
+    let full_path = path.to_string_lossy();
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let matches = |pattern: &str| {
+        if pattern.contains('/') {
+            wildcard_match(pattern, &full_path)
+        } else {
+            wildcard_match(pattern, &file_name)
+        }
+    };
+    if GENERATED_FILE_PATTERNS
+        .iter()
+        .any(|pattern| matches(pattern))
+        || extra_patterns.iter().any(|pattern| matches(pattern))
+    {
+        return true;
+    }
+    matches!(
+        AttrValue::from_string(
+            repo.get_attr(path, "linguist-generated", AttrCheckFlags::INDEX_THEN_FILE)
+                .ok()
+                .flatten()
+        ),
+        AttrValue::True
+    )
+}
+
+/// `*` and `?` wildcards, as in ssh `Host` patterns and branch globs. `*` also matches `/`
+pub(crate) fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, star_text)) = backtrack {
+            p = star + 1;
+            t = star_text + 1;
+            backtrack = Some((star, star_text + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
 /// # Extension Precedence (for Windows)
 /// The module checks for hook files in the following order:
 /// 1. Shell scripts with no extension (direct executable)
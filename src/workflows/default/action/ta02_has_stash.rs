@@ -1,7 +1,6 @@
 use crate::{
     bgit_error::BGitError,
     events::{git_add::GitAdd, AtomicEvent},
-    rules::{a01_git_install::IsGitInstalledLocally, Rule},
     step::{ActionStep, Step},
 };
 use git2::Repository;
@@ -37,8 +36,7 @@ impl ActionStep for HasStash {
                 })
                 .is_ok();
 
-            let mut git_add_event = GitAdd::new();
-            git_add_event.add_pre_check_rule(Box::new(IsGitInstalledLocally::new()));
+            let git_add_event = GitAdd::new();
             git_add_event.execute()?;
 
             Ok(Step::Stop)